@@ -0,0 +1,40 @@
+use tokio::process::Command;
+
+/// Speak a short status message through speech-dispatcher
+///
+/// speech-dispatcher is the speech service Orca uses, so announcements are
+/// voiced with the user's screen reader voice and settings and queue politely
+/// with its own output. Waits until the message has been spoken, so e.g. the
+/// "recording" announcement isn't captured by the microphone.
+pub async fn announce(message: &str) {
+    tracing::debug!("Announcing: {}", message);
+
+    let result = Command::new("spd-say")
+        .args(["--application-name", "dictator", "--wait", message])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .status()
+        .await;
+
+    match result {
+        Ok(status) if !status.success() => {
+            tracing::warn!("spd-say exited with {}", status);
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to run spd-say for announcement: {}", e),
+    }
+}
+
+/// Show a desktop notification via `notify-send`, without waiting for it
+pub fn notify(summary: &str, body: &str) {
+    let args = [
+        "--app-name=Dictator".to_string(),
+        summary.to_string(),
+        body.to_string(),
+    ];
+    tokio::spawn(async move {
+        if let Err(e) = Command::new("notify-send").args(args).status().await {
+            tracing::warn!("Failed to run notify-send: {}", e);
+        }
+    });
+}
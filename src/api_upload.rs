@@ -0,0 +1,289 @@
+use crate::audio::{AudioFormat, RecordedAudio, pcm_bytes};
+use crate::config::{Config, TimestampGranularity};
+use crate::transcription::{ApiConfig, Transcript, TranscriptionConfig};
+use anyhow::{Context, Result};
+use async_openai::config::Config as _;
+use futures::channel::mpsc as body_channel;
+use reqwest::multipart::{Form, Part};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Longest a streamed upload may run, as the request spans the whole recording
+const MAX_UPLOAD_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Uploads recordings to the OpenAI-compatible API
+///
+/// The multipart request is built here rather than by `async_openai`, whose
+/// typed requests can't carry `api_body_fields` or a streamed file.
+///
+/// With `stream_upload`, the request is sent with a chunked body as soon as
+/// recording starts, and each chunk is written to it as WAV as it is captured.
+/// When recording stops only the last chunk is left to send before the server
+/// starts transcribing.
+#[derive(Clone)]
+pub struct ApiUpload {
+    http: reqwest::Client,
+    api: ApiConfig,
+    options: DecodingOptions,
+    format: AudioFormat,
+    timeout: Duration,
+    stream: bool,
+}
+
+/// Decoding settings sent as form fields with every upload
+#[derive(Clone, Debug, Default, PartialEq)]
+struct DecodingOptions {
+    temperature: Option<f32>,
+    timestamp_granularities: Vec<TimestampGranularity>,
+    /// `api_body_fields`, rendered as form text
+    fields: Vec<(String, String)>,
+}
+
+impl DecodingOptions {
+    fn from_config(config: &Config) -> Self {
+        let mut fields: Vec<(String, String)> = config
+            .api_body_fields
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        fields.sort();
+        Self {
+            temperature: config.temperature,
+            timestamp_granularities: config.timestamp_granularities.clone(),
+            fields,
+        }
+    }
+}
+
+impl ApiUpload {
+    pub fn new(config: &Config, http: reqwest::Client, api: ApiConfig) -> Self {
+        Self {
+            http,
+            api,
+            options: DecodingOptions::from_config(config),
+            format: config.audio_format(),
+            timeout: Duration::from_secs(config.timeout),
+            stream: config.stream_upload,
+        }
+    }
+
+    /// Whether recordings are uploaded while recording (`stream_upload`)
+    pub fn streams(&self) -> bool {
+        self.stream
+    }
+
+    /// Upload a finished recording
+    pub async fn transcribe(
+        &self,
+        audio: &RecordedAudio,
+        config: &TranscriptionConfig,
+    ) -> Result<Transcript> {
+        tracing::info!("Transcribing {:?}", audio);
+
+        let file = match audio {
+            RecordedAudio::File(file) => {
+                let bytes = tokio::fs::read(file.path())
+                    .await
+                    .with_context(|| format!("Failed to read recording {:?}", file.path()))?;
+                // The extension tells the server the format
+                let name = file
+                    .path()
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "recording.wav".to_string());
+                Part::bytes(bytes).file_name(name)
+            }
+            RecordedAudio::Memory(bytes) => Part::bytes(bytes.clone()).file_name("recording.wav"),
+        };
+
+        let (form, path) = self.form(config, file);
+        let response = self
+            .http
+            .post(self.api.url(path))
+            .query(&self.api.query())
+            .headers(self.api.headers())
+            .multipart(form)
+            .send()
+            .await
+            .context("Transcription API call failed")?;
+
+        let transcript = read_transcript(response).await?;
+        tracing::info!(
+            "Transcription complete: {} chars in {} segments",
+            transcript.text.len(),
+            transcript.segments.len()
+        );
+        Ok(transcript)
+    }
+
+    /// Send chunks until the channel closes, then wait for the transcript
+    pub async fn stream(
+        self,
+        config: TranscriptionConfig,
+        mut audio: mpsc::UnboundedReceiver<Vec<f32>>,
+    ) -> Result<Transcript> {
+        let (body, body_rx) = body_channel::unbounded::<std::io::Result<Vec<u8>>>();
+        let _ = body.unbounded_send(Ok(wav_header(self.format)));
+
+        let file = Part::stream(reqwest::Body::wrap_stream(body_rx))
+            .file_name("recording.wav")
+            .mime_str("audio/wav")
+            .context("Invalid upload content type")?;
+        let (form, path) = self.form(&config, file);
+
+        tracing::info!("Streaming the recording to {}", self.api.url(path));
+        let request = self
+            .http
+            .post(self.api.url(path))
+            .query(&self.api.query())
+            .headers(self.api.headers())
+            .multipart(form)
+            // The client's timeout would cut off long recordings; it applies
+            // from the moment the recording stops instead
+            .timeout(MAX_UPLOAD_DURATION)
+            .send();
+        tokio::pin!(request);
+
+        // Forward the recording as it arrives
+        loop {
+            tokio::select! {
+                chunk = audio.recv() => match chunk {
+                    Some(chunk) => {
+                        let _ = body.unbounded_send(Ok(pcm_bytes(&chunk)));
+                    }
+                    None => break,
+                },
+                response = &mut request => {
+                    let response = response.context("Streaming upload failed")?;
+                    anyhow::bail!(
+                        "The server answered {} before the recording ended",
+                        response.status()
+                    );
+                }
+            }
+        }
+        body.close_channel();
+
+        let response = tokio::time::timeout(self.timeout, request)
+            .await
+            .context("Timed out waiting for the transcription")?
+            .context("Streaming upload failed")?;
+        let transcript = read_transcript(response).await?;
+        tracing::info!("Transcription complete: {} chars", transcript.text.len());
+        Ok(transcript)
+    }
+
+    /// The form for uploading `file`, and the endpoint to post it to
+    fn form(&self, config: &TranscriptionConfig, file: Part) -> (Form, &'static str) {
+        let mut form = Form::new()
+            .text("model", config.model.clone())
+            .text("response_format", "verbose_json")
+            .part("file", file);
+        if !config.prompt.is_empty() {
+            form = form.text("prompt", config.prompt.clone());
+        }
+        if let Some(temperature) = self.options.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+        let path = if config.translate {
+            "/audio/translations"
+        } else {
+            if !config.language.is_empty() {
+                form = form.text("language", config.language.clone());
+            }
+            for granularity in &self.options.timestamp_granularities {
+                form = form.text("timestamp_granularities[]", granularity.name());
+            }
+            "/audio/transcriptions"
+        };
+        for (name, value) in &self.options.fields {
+            form = form.text(name.clone(), value.clone());
+        }
+        (form, path)
+    }
+}
+
+/// The transcript from a successful response, or the server's error
+async fn read_transcript(response: reqwest::Response) -> Result<Transcript> {
+    let status = response.status();
+    let body = response
+        .bytes()
+        .await
+        .context("Failed to read transcription response")?;
+    if !status.is_success() {
+        anyhow::bail!(
+            "Transcription API returned {}: {}",
+            status,
+            String::from_utf8_lossy(&body)
+        );
+    }
+    Transcript::from_verbose_json(&body)
+}
+
+/// Header of a 16-bit PCM WAV file of unknown length
+///
+/// The RIFF and data sizes are set to the maximum, as is usual for streamed
+/// WAV; decoders read until the body ends.
+fn wav_header(format: AudioFormat) -> Vec<u8> {
+    let channels = format.channels;
+    let block_align = channels * 2;
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&u32::MAX.to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&format.sample_rate.to_le_bytes());
+    header.extend_from_slice(&(format.sample_rate * u32::from(block_align)).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&u32::MAX.to_le_bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streamed_wav() {
+        let header = wav_header(AudioFormat::default());
+        assert_eq!(header.len(), 44);
+        assert_eq!(&header[22..24], &1u16.to_le_bytes());
+        assert_eq!(&header[24..28], &16000u32.to_le_bytes());
+        assert_eq!(&header[28..32], &32000u32.to_le_bytes());
+        assert_eq!(&header[36..40], b"data");
+    }
+
+    #[test]
+    fn test_decoding_options() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "temperature": 0.0,
+            "timestamp_granularities": ["segment", "word"],
+            "api_body_fields": { "vad_filter": true, "beam_size": 5, "hotwords": "kubectl" }
+        }))
+        .unwrap();
+        let options = DecodingOptions::from_config(&config);
+        assert_eq!(options.temperature, Some(0.0));
+        assert_eq!(
+            options.timestamp_granularities,
+            [TimestampGranularity::Segment, TimestampGranularity::Word]
+        );
+        assert_eq!(
+            options.fields,
+            [
+                ("beam_size".to_string(), "5".to_string()),
+                ("hotwords".to_string(), "kubectl".to_string()),
+                ("vad_filter".to_string(), "true".to_string()),
+            ]
+        );
+    }
+}
@@ -1,14 +1,39 @@
-use crate::audio::{AudioFeedback, AudioFormat, Recorder, feedback::FeedbackSoundType};
-use crate::config::Config;
+use crate::announce;
+use crate::archive::Archive;
+use crate::assets;
+use crate::audio::{
+    AudioFeedback, AudioHost, InputLevel, JackOptions, RecordedAudio, Recorder, RecorderOptions,
+    feedback::FeedbackSoundType, recorder,
+};
+use crate::compose::{self, ComposeBuffer};
+use crate::config::{
+    Config, OfflineAction, PasteMode, ProfileSettings, RecordingLimitAction, RefineAction,
+    ShortcutMode, TriggerOn,
+};
+use crate::control::{self, Status};
+use crate::corrections;
+use crate::focused_window;
+use crate::history::{self, HistoryEntry, LatencyBreakdown};
 use crate::hooks;
-use crate::text_injection;
+use crate::offline_queue::{self, QueueStore, QueuedDictation};
+use crate::prompt::{self, RecentContext};
+use crate::remote_desktop::PortalKeyboard;
+use crate::scratch::ScratchCommand;
+use crate::shortcuts;
+use crate::shortcuts::ShortcutEvent;
+use crate::text_injection::{self, KeyCommands, Segment};
 use crate::text_processing::TextProcessor;
-use crate::transcription;
-use crate::{shortcuts, transcription::TranscriptionConfig};
+use crate::transcription::{
+    Fallback, StreamingSession, Transcriber, Transcript, TranscriptionConfig,
+};
 
 use anyhow::Result;
-use std::collections::HashMap;
-use tokio::sync::mpsc;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, mpsc, watch};
+use tracing::Instrument;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum AppState {
@@ -17,66 +42,676 @@ pub enum AppState {
     Processing,
 }
 
+impl AppState {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Recording => "recording",
+            Self::Processing => "processing",
+        }
+    }
+}
+
 pub struct App {
     state: AppState,
     config: Config,
+    active_profile: Option<String>,
+    /// Language chosen with the cycle_language action, if any
+    language_override: Option<String>,
     recorder: Recorder,
-    transcription_client: async_openai::Client<async_openai::config::OpenAIConfig>,
+    transcriber: Transcriber,
+    /// Tried in order when `transcriber` fails
+    fallbacks: Vec<Fallback>,
     text_processor: TextProcessor,
+    text_processor_settings: ProfileSettings,
     audio_feedback: AudioFeedback,
-    shortcut_rx: mpsc::Receiver<()>,
+    compose: ComposeBuffer,
+    portal_keyboard: Option<PortalKeyboard>,
+    /// Text of the last injection, for learning from the user's corrections
+    last_injected: Option<String>,
+    /// A scratch command starting a dictation, which undoes the one before
+    scratch: Option<ScratchCommand>,
+    /// Text the last dictation typed without pressing keys, which
+    /// `scratch_undo_injection` may delete
+    scratchable: Option<String>,
+    /// Audio of the last dictation, kept for the retry action
+    last_recording: Option<RecordedAudio>,
+    /// Recognition of the current recording, for backends that stream
+    stream: Option<StreamingSession>,
+    /// Where recordings and transcripts are kept (`archive_dir`)
+    archive: Option<Archive>,
+    /// When the current recording started, for the recording limit
+    recording_started: Option<Instant>,
+    /// When the current recording was paused, if it is
+    paused_at: Option<Instant>,
+    limit_warned: bool,
+    /// When the recording shortcut last acted, for `shortcut_debounce_ms`
+    last_shortcut: Option<Instant>,
+    shortcut_rx: mpsc::Receiver<ShortcutEvent>,
+    /// Notified when the cancel shortcut is pressed
+    cancel: Arc<Notify>,
+    /// Recent dictations, fed back as the prompt with `context_prompt`
+    context: RecentContext,
+    /// Recordings started so far, to tell whether a refined draft is the latest
+    dictations: u64,
+    refined_tx: mpsc::UnboundedSender<Refined>,
+    refined_rx: mpsc::UnboundedReceiver<Refined>,
+    /// Reported to `dictator status`
+    status: watch::Sender<Status>,
+    /// Recordings waiting for the service to come back (`offline_queue`)
+    offline_queue: VecDeque<Queued>,
+    /// Keeps `offline_queue` across restarts, unless `audio_in_memory`
+    offline_store: Option<QueueStore>,
+    /// When to next check whether the service is back
+    offline_retry_at: Option<Instant>,
+    /// Whether the background health check reached the service last time
+    /// (`health_check_interval_secs`)
+    health: Option<watch::Receiver<bool>>,
 }
 
 fn build_audio_feedback(config: &Config) -> AudioFeedback {
+    let search_dirs = assets::search_dirs(&config.assets_dirs);
+
     let mut paths = HashMap::new();
-    paths.insert(FeedbackSoundType::Start, config.start_sound_path.clone());
-    paths.insert(FeedbackSoundType::Stop, config.stop_sound_path.clone());
-    paths.insert(
-        FeedbackSoundType::Complete,
-        config.complete_sound_path.clone(),
-    );
+    for (sound_type, path) in [
+        (FeedbackSoundType::Start, &config.start_sound_path),
+        (FeedbackSoundType::Stop, &config.stop_sound_path),
+        (FeedbackSoundType::Complete, &config.complete_sound_path),
+        (FeedbackSoundType::Warning, &config.warning_sound_path),
+    ]
+    .into_iter()
+    .chain(
+        config
+            .discard_sound_path
+            .as_ref()
+            .map(|path| (FeedbackSoundType::Discarded, path)),
+    )
+    .chain(
+        config
+            .unavailable_sound_path
+            .as_ref()
+            .map(|path| (FeedbackSoundType::Unavailable, path)),
+    ) {
+        match assets::resolve(path, &search_dirs) {
+            Some(resolved) => {
+                tracing::debug!("{:?} sound resolved to {:?}", sound_type, resolved);
+                paths.insert(sound_type, resolved);
+            }
+            None if config.audio_feedback => {
+                tracing::warn!(
+                    "{:?} sound {:?} not found in any assets directory: {:?}",
+                    sound_type,
+                    path,
+                    search_dirs
+                );
+            }
+            None => {}
+        }
+    }
     AudioFeedback::new(paths)
 }
 
+/// Set up `archive_dir`, first deleting dictations past the retention limits
+fn build_archive(config: &Config) -> Option<Archive> {
+    let archive = Archive::new(
+        config.archive_dir.clone()?,
+        config.archive_max_files,
+        config.archive_max_age_days,
+    );
+    match archive.prune() {
+        Ok(0) => {}
+        Ok(removed) => tracing::info!("Removed {} old dictations from the archive", removed),
+        Err(e) => tracing::warn!("Failed to clean up the archive: {:#}", e),
+    }
+    Some(archive)
+}
+
+pub fn build_text_processor(settings: &ProfileSettings) -> TextProcessor {
+    // Translations are in English whatever language was spoken
+    let language = if settings.translate {
+        Some("en")
+    } else {
+        settings.language.as_deref()
+    };
+    TextProcessor::new(&settings.word_overrides, language)
+        .with_regex_rules(&settings.regex_rules)
+        .with_punctuation_overrides(&settings.punctuation_overrides)
+        .with_punctuation_commands(settings.punctuation_commands)
+        .with_emoji_commands(&settings.emoji_commands)
+        .with_symbol_commands(&settings.symbol_commands, settings.symbol_commands_stage)
+        .with_snippets(&settings.snippets)
+        .with_scratch_phrase(settings.scratch_phrase.as_deref())
+        .with_terminal_punctuation(settings.terminal_punctuation.as_deref())
+        .with_number_conversion(settings.convert_numbers)
+        .with_sentence_capitalization(settings.capitalize_sentences)
+        .with_case_commands(settings.case_commands)
+        .with_capitalization_commands(settings.capitalization_commands)
+}
+
+/// What was injected for a dictation, kept while `refine_model` transcribes it again
+#[derive(Clone)]
+struct Draft {
+    text: String,
+    /// Whether key actions were pressed, after which the draft can't be replaced
+    pressed_keys: bool,
+    settings: ProfileSettings,
+}
+
+/// The health check's new verdict; never resolves without health checks
+async fn health_changed(health: &mut Option<watch::Receiver<bool>>) -> bool {
+    if let Some(health) = health
+        && health.changed().await.is_ok()
+    {
+        return *health.borrow_and_update();
+    }
+    std::future::pending().await
+}
+
+/// Recordings a previous run queued, with the settings they were dictated with
+fn load_offline_queue(config: &Config, store: &QueueStore) -> VecDeque<Queued> {
+    let queued = match store.load() {
+        Ok(queued) => queued,
+        Err(e) => {
+            tracing::warn!("Failed to load queued recordings: {:#}", e);
+            return VecDeque::new();
+        }
+    };
+    if !queued.is_empty() {
+        tracing::info!("Found {} recordings queued by a previous run", queued.len());
+    }
+
+    queued
+        .into_iter()
+        .filter_map(|(path, dictation)| {
+            let audio = recorder::copy_to_temp_file(&path, config.temp_dir.as_deref())
+                .inspect_err(|e| tracing::warn!("Skipping queued recording: {:#}", e))
+                .ok()?;
+            let mut settings = config.profile_settings(dictation.profile.as_deref());
+            if dictation.language.is_some() {
+                settings.language = dictation.language;
+            }
+            Some(Queued {
+                audio,
+                settings,
+                saved: Some(path),
+            })
+        })
+        .collect()
+}
+
+/// A recording kept while the transcription service is unreachable
+struct Queued {
+    audio: RecordedAudio,
+    /// The settings it was dictated with
+    settings: ProfileSettings,
+    /// Where the store keeps it until it is delivered
+    saved: Option<PathBuf>,
+}
+
+/// A dictation transcribed again with `refine_model`
+struct Refined {
+    /// Which dictation, from `App::dictations`
+    dictation: u64,
+    audio: RecordedAudio,
+    draft: Draft,
+    result: Result<Transcript>,
+}
+
+/// The text of a dictation without its key presses
+fn segments_text(segments: &[Segment]) -> String {
+    let texts: Vec<&str> = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Text(text) => Some(text.as_str()),
+            Segment::Keys(_) => None,
+        })
+        .collect();
+    texts.join(" ")
+}
+
+/// Give up on a transcription attempt after `limit`
+async fn within(
+    limit: Duration,
+    attempt: impl Future<Output = Result<Transcript>>,
+) -> Result<Transcript> {
+    tokio::time::timeout(limit, attempt)
+        .await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("timed out after {:?}", limit)))
+}
+
+/// What to ask the backend for, given the settings of a dictation and the
+/// recent dictations `context_prompt` feeds back
+pub fn transcription_config(
+    config: &Config,
+    settings: &ProfileSettings,
+    context: &str,
+) -> TranscriptionConfig {
+    TranscriptionConfig {
+        model: settings.model.clone(),
+        prompt: prompt::build(
+            settings.whisper_prompt.as_deref().unwrap_or_default(),
+            &vocabulary(config, settings),
+            context,
+            config.prompt_max_chars,
+        ),
+        language: settings.language.clone().unwrap_or_default(),
+        keywords: keywords(&settings.word_overrides),
+        translate: settings.translate,
+    }
+}
+
+/// Terms to bias recognition towards: `vocabulary`, then with
+/// `vocabulary_from_overrides` the spellings `word_overrides` produce
+fn vocabulary(config: &Config, settings: &ProfileSettings) -> Vec<String> {
+    let mut vocabulary = settings.vocabulary.clone();
+    if config.vocabulary_from_overrides {
+        let mut spellings: Vec<String> = settings.word_overrides.values().cloned().collect();
+        spellings.sort();
+        vocabulary.extend(spellings);
+    }
+    vocabulary
+}
+
+/// Words to boost in recognition: the phrases `word_overrides` listens for
+fn keywords(word_overrides: &HashMap<String, String>) -> Vec<String> {
+    let mut keywords: Vec<String> = word_overrides.keys().cloned().collect();
+    keywords.sort();
+    keywords
+}
+
 impl App {
     pub async fn new(config: Config) -> Result<Self> {
-        let transcription_client = transcription::create_client(&config.api_url, &config.api_key);
-        if let Err(e) = transcription::check_availability(&transcription_client).await {
-            tracing::warn!("Transcription service unavailable at startup: {}", e);
+        let transcriber = Transcriber::new(&config).await?;
+        // Don't hold up startup on the network; the result is only informational
+        let check = transcriber.clone();
+        tokio::spawn(async move {
+            if let Err(e) = check.check_availability().await {
+                tracing::warn!("Transcription service unavailable at startup: {}", e);
+            }
+        });
+        if let Some(interval) = config.keepalive_interval_secs {
+            transcriber.spawn_keepalive(interval);
         }
+        let health = (config.health_check_interval_secs > 0)
+            .then(|| transcriber.spawn_health_check(config.health_check_interval_secs));
+        let fallbacks = Fallback::load_all(&config).await?;
 
-        let recorder = Self::setup_audio_pipeline();
+        let temp_dir = config.temp_dir.clone().unwrap_or_else(std::env::temp_dir);
+        match recorder::sweep_stale_temp_files(&temp_dir) {
+            Ok(0) => {}
+            Ok(removed) => tracing::info!("Removed {} stale temporary recordings", removed),
+            Err(e) => tracing::warn!("Failed to clean up temporary recordings: {:#}", e),
+        }
 
-        let text_processor = TextProcessor::new(&config.word_overrides);
+        let mut recorder = Self::setup_audio_pipeline(&config);
+        if let Err(e) = recorder.open_pre_roll() {
+            tracing::warn!("Failed to open microphone for pre-roll: {:#}", e);
+        }
+
+        let active_profile = config.active_profile.clone();
+        let settings = config.profile_settings(active_profile.as_deref());
+        if let Some(name) = &active_profile {
+            tracing::info!(
+                "Using profile {:?} (language: {:?})",
+                name,
+                settings.language
+            );
+        }
+        let text_processor = build_text_processor(&settings);
+        let archive = build_archive(&config);
         let audio_feedback = build_audio_feedback(&config);
-        let shortcut_rx = Self::setup_keyboard_monitoring()?;
+        let compose =
+            ComposeBuffer::new(&config.compose_send_phrase, &config.compose_scratch_phrase);
+        let scratch = config
+            .scratch_phrase
+            .as_deref()
+            .and_then(ScratchCommand::new);
+        let cancel = Arc::new(Notify::new());
+        let (status, status_rx) = watch::channel(Status::default());
+        let shortcut_rx =
+            Self::setup_keyboard_monitoring(&config, cancel.clone(), recorder.levels(), status_rx)?;
+
+        let (refined_tx, refined_rx) = mpsc::unbounded_channel();
+        let context = RecentContext::new(config.context_sentences, config.context_max_chars);
+        let offline_store = (config.offline_queue && !config.audio_in_memory)
+            .then(QueueStore::open)
+            .transpose()?;
+        let offline_queue = match &offline_store {
+            Some(store) => load_offline_queue(&config, store),
+            None => VecDeque::new(),
+        };
+        // Drain what a previous run left as soon as the service answers
+        let offline_retry_at = (!offline_queue.is_empty()).then(Instant::now);
 
         tracing::info!("Ready! Listening for global shortcut.");
 
-        Ok(Self {
+        let app = Self {
             state: AppState::Idle,
             config,
+            active_profile,
+            language_override: None,
             recorder,
-            transcription_client,
+            transcriber,
+            fallbacks,
             text_processor,
+            text_processor_settings: settings,
             audio_feedback,
+            compose,
+            portal_keyboard: None,
+            last_injected: None,
+            scratch,
+            scratchable: None,
+            last_recording: None,
+            stream: None,
+            archive,
+            recording_started: None,
+            paused_at: None,
+            limit_warned: false,
+            last_shortcut: None,
             shortcut_rx,
-        })
+            cancel,
+            context,
+            dictations: 0,
+            refined_tx,
+            refined_rx,
+            status,
+            offline_queue,
+            offline_store,
+            offline_retry_at,
+            health,
+        };
+        app.publish_status();
+        Ok(app)
+    }
+
+    fn set_state(&mut self, state: AppState) {
+        self.state = state;
+        self.publish_status();
+    }
+
+    /// Update what `dictator status` reports
+    fn publish_status(&self) {
+        self.status.send_replace(Status {
+            state: self.state.name(),
+            profile: self.active_profile.clone(),
+            language: self.current_language(),
+            queued: self.offline_queue.len(),
+            unreachable: !self.service_reachable(),
+        });
+    }
+
+    /// What the last health check found; assumed reachable without checks
+    fn service_reachable(&self) -> bool {
+        self.health.as_ref().is_none_or(|health| *health.borrow())
+    }
+
+    /// The language chosen with cycle_language, or else the active profile's
+    fn current_language(&self) -> Option<String> {
+        match &self.language_override {
+            Some(language) => Some(language.clone()),
+            None => {
+                self.config
+                    .profile_settings(self.active_profile.as_deref())
+                    .language
+            }
+        }
+    }
+
+    /// Live input level while recording
+    pub fn input_levels(&self) -> watch::Receiver<InputLevel> {
+        self.recorder.levels()
     }
 
     pub async fn run(mut self) -> Result<()> {
         loop {
             tracing::debug!("Main loop: waiting for event");
-            if self.shortcut_rx.recv().await.is_some() {
-                tracing::debug!("Main loop: received shortcut signal");
-                if let Err(e) = self.handle_toggle().await {
-                    tracing::error!("Error handling toggle: {}", e);
+            let timer = self.next_recording_timer();
+            let sleep = tokio::time::sleep_until(timer.unwrap_or_else(Instant::now).into());
+            let retry_at = self.offline_retry_at;
+            let offline_retry =
+                tokio::time::sleep_until(retry_at.unwrap_or_else(Instant::now).into());
+            let cancel = self.cancel.clone();
+            let event = tokio::select! {
+                event = self.shortcut_rx.recv() => event,
+                _ = cancel.notified() => {
+                    if let Err(e) = self.handle_cancel().await {
+                        tracing::error!("Error cancelling recording: {}", e);
+                    }
+                    continue;
+                }
+                Some(refined) = self.refined_rx.recv() => {
+                    self.handle_refined(refined).await;
+                    continue;
+                }
+                failed = self.recorder.device_lost() => {
+                    self.handle_device_lost(failed).await;
+                    continue;
+                }
+                _ = sleep, if timer.is_some() => {
+                    if let Err(e) = self.handle_recording_timer().await {
+                        tracing::error!("Error stopping recording at limit: {}", e);
+                        self.announce_if_enabled(&format!("Dictation failed: {}", e))
+                            .await;
+                    }
+                    continue;
+                }
+                reachable = health_changed(&mut self.health) => {
+                    self.handle_health(reachable);
+                    continue;
+                }
+                _ = offline_retry, if retry_at.is_some() => {
+                    self.retry_offline_queue().await;
+                    continue;
+                }
+            };
+            match event {
+                Some(
+                    event @ (ShortcutEvent::Toggle
+                    | ShortcutEvent::RecordPressed
+                    | ShortcutEvent::RecordReleased),
+                ) => {
+                    tracing::debug!("Main loop: received shortcut signal {:?}", event);
+                    if let Err(e) = self.handle_record_shortcut(event).await {
+                        tracing::error!("Error handling toggle: {}", e);
+                        self.announce_if_enabled(&format!("Dictation failed: {}", e))
+                            .await;
+                    }
+                    tracing::debug!("Main loop: handle_toggle completed");
+                }
+                Some(ShortcutEvent::MarkCorrected) => {
+                    if let Err(e) = self.handle_mark_corrected().await {
+                        tracing::warn!("Failed to record correction: {}", e);
+                    }
+                }
+                Some(ShortcutEvent::Retry) => {
+                    if let Err(e) = self.handle_retry().await {
+                        tracing::error!("Error retrying last recording: {}", e);
+                        self.announce_if_enabled(&format!("Dictation failed: {}", e))
+                            .await;
+                    }
+                }
+                Some(ShortcutEvent::SwitchProfile(name)) => self.switch_profile(name).await,
+                Some(ShortcutEvent::CycleLanguage) => self.cycle_language().await,
+                Some(ShortcutEvent::ClearContext) => self.clear_context().await,
+                Some(ShortcutEvent::Pause) => {
+                    if let Err(e) = self.toggle_pause().await {
+                        tracing::error!("Error pausing recording: {}", e);
+                    }
+                }
+                // Cancel presses arrive through `self.cancel` instead
+                Some(ShortcutEvent::Cancel) | None => {}
+            }
+        }
+    }
+
+    /// Discard the current recording; outside a recording there is nothing to do
+    async fn handle_cancel(&mut self) -> Result<()> {
+        if self.state != AppState::Recording {
+            tracing::debug!("Cancel pressed while {:?}, ignoring", self.state);
+            return Ok(());
+        }
+
+        tracing::info!("Cancelling recording");
+        self.discard_recording("Cancelled").await
+    }
+
+    /// Stop recording and throw the audio away
+    async fn discard_recording(&mut self, announcement: &str) -> Result<()> {
+        self.set_state(AppState::Idle);
+        self.recording_started = None;
+        self.paused_at = None;
+        let result = self.recorder.cancel().await;
+        self.stream = None;
+
+        self.play_feedback_if_enabled(FeedbackSoundType::Stop).await;
+        self.announce_if_enabled(announcement).await;
+        self.run_hook_if_configured("on_recording_stop", &self.config.on_recording_stop.clone());
+
+        result
+    }
+
+    /// Switch to another microphone when the one in use disappears, e.g. a
+    /// Bluetooth headset disconnecting mid-recording
+    ///
+    /// If no other input is available, the recording is stopped and what was
+    /// captured so far is transcribed.
+    async fn handle_device_lost(&mut self, failed: String) {
+        tracing::warn!("Input device {:?} disappeared", failed);
+        let recording = self.state == AppState::Recording;
+
+        match self.recorder.switch_device(&failed) {
+            Ok(()) if recording => {
+                self.play_feedback_if_enabled(FeedbackSoundType::Warning)
+                    .await;
+                self.announce_if_enabled("Microphone lost, switched to another")
+                    .await;
+            }
+            Ok(()) => {}
+            Err(e) => {
+                tracing::error!("No input device to fall back to: {:#}", e);
+                if recording {
+                    self.announce_if_enabled("Microphone lost").await;
+                    if let Err(e) = self.handle_stop_and_process().await {
+                        tracing::error!("Error processing interrupted recording: {}", e);
+                    }
                 }
-                tracing::debug!("Main loop: handle_toggle completed");
             }
         }
     }
 
+    /// When the recording limit next needs attention: the warning, then the limit
+    fn next_recording_timer(&self) -> Option<Instant> {
+        let started = self.recording_started?;
+        if self.state != AppState::Recording
+            || self.paused_at.is_some()
+            || self.config.max_recording_secs == 0
+        {
+            return None;
+        }
+
+        let limit = started + Duration::from_secs(self.config.max_recording_secs);
+        if self.limit_warned {
+            Some(limit)
+        } else {
+            Some(limit - Duration::from_secs(self.config.recording_warning_secs))
+        }
+    }
+
+    async fn handle_recording_timer(&mut self) -> Result<()> {
+        if !self.limit_warned {
+            self.limit_warned = true;
+            tracing::warn!(
+                "Recording stops in {}s (max_recording_secs: {})",
+                self.config.recording_warning_secs,
+                self.config.max_recording_secs
+            );
+            self.play_feedback_if_enabled(FeedbackSoundType::Warning)
+                .await;
+            self.run_hook_if_configured(
+                "on_recording_limit_warning",
+                &self.config.on_recording_limit_warning.clone(),
+            );
+            return Ok(());
+        }
+
+        tracing::warn!(
+            "Recording limit of {}s reached, stopping",
+            self.config.max_recording_secs
+        );
+        match self.config.recording_limit_action {
+            RecordingLimitAction::Transcribe => self.handle_stop_and_process().await,
+            RecordingLimitAction::Discard => {
+                self.discard_recording("Recording limit reached, discarded")
+                    .await
+            }
+        }
+    }
+
+    /// Compare the clipboard, holding the user's corrected version of the last
+    /// dictation, with what was injected and record the substitutions
+    async fn handle_mark_corrected(&mut self) -> Result<()> {
+        let Some(injected) = &self.last_injected else {
+            tracing::info!("No dictation to compare the correction against");
+            return Ok(());
+        };
+
+        let corrected = text_injection::read_clipboard().await?;
+        if corrected.is_empty() || corrected == *injected {
+            tracing::info!("Clipboard holds no corrected text");
+            return Ok(());
+        }
+
+        let found = corrections::record(injected, &corrected)?;
+        tracing::info!(
+            "Recorded {} correction(s); see `dictator corrections`",
+            found
+        );
+        self.announce_if_enabled(&format!("{} corrections recorded", found))
+            .await;
+        Ok(())
+    }
+
+    async fn handle_record_shortcut(&mut self, event: ShortcutEvent) -> Result<()> {
+        let toggle_edge = match self.config.trigger_on {
+            TriggerOn::Press => ShortcutEvent::RecordPressed,
+            TriggerOn::Release => ShortcutEvent::RecordReleased,
+        };
+        match (self.config.shortcut_mode, event) {
+            (_, ShortcutEvent::Toggle) if !self.debounced() => self.handle_toggle().await,
+            (ShortcutMode::Toggle, event) if event == toggle_edge && !self.debounced() => {
+                self.handle_toggle().await
+            }
+            (ShortcutMode::Hold, ShortcutEvent::RecordPressed)
+                if self.state == AppState::Idle && !self.debounced() =>
+            {
+                self.handle_start_recording().await
+            }
+            // The recording limit may already have stopped this recording. Not
+            // debounced: the release must end the recording its press started
+            (ShortcutMode::Hold, ShortcutEvent::RecordReleased)
+                if self.state == AppState::Recording =>
+            {
+                self.handle_stop_and_process().await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether a recording shortcut activation comes too soon after the last
+    /// one (`shortcut_debounce_ms`); otherwise it is recorded as the last one
+    fn debounced(&mut self) -> bool {
+        let debounce = Duration::from_millis(self.config.shortcut_debounce_ms);
+        if self
+            .last_shortcut
+            .is_some_and(|last| last.elapsed() < debounce)
+        {
+            tracing::debug!("Ignoring recording shortcut within debounce interval");
+            return true;
+        }
+        self.last_shortcut = Some(Instant::now());
+        false
+    }
+
     async fn handle_toggle(&mut self) -> Result<()> {
         tracing::debug!("handle_toggle: current state = {:?}", self.state);
 
@@ -95,55 +730,421 @@ impl App {
         }
     }
 
-    fn build_transcription_config(&self) -> TranscriptionConfig {
-        TranscriptionConfig {
-            model: self.config.model.clone(),
-            prompt: self.config.whisper_prompt.clone().unwrap_or_default(),
-            language: self.config.language.clone().unwrap_or_default(),
+    /// Make a profile active for the following dictations
+    ///
+    /// The text processor picks up the profile's settings on the next
+    /// dictation; only the audio source is applied straight away, so a
+    /// pre-roll stream starts buffering from the new source.
+    async fn switch_profile(&mut self, name: String) {
+        tracing::info!("Switching to profile {:?}", name);
+        self.announce_if_enabled(&format!("Profile {}", name)).await;
+        let source = self.config.profile_settings(Some(&name)).audio_source;
+        self.active_profile = Some(name);
+        self.language_override = None;
+        self.publish_status();
+        // The recent dictations were likely about something else
+        self.context.clear();
+
+        if self.state == AppState::Idle
+            && let Err(e) = self.recorder.set_audio_source(source)
+        {
+            tracing::error!("Failed to switch audio source: {:#}", e);
+        }
+    }
+
+    /// Forget the recent dictations `context_prompt` feeds back
+    async fn clear_context(&mut self) {
+        tracing::info!("Clearing the dictation context");
+        self.context.clear();
+        self.announce_if_enabled("Context cleared").await;
+    }
+
+    /// Pause the current recording, or resume it if paused
+    ///
+    /// The paused stretch is left out of the audio, so the whole dictation is
+    /// still transcribed in one go when it stops.
+    async fn toggle_pause(&mut self) -> Result<()> {
+        if self.state != AppState::Recording {
+            tracing::debug!("Pause pressed while {:?}, ignoring", self.state);
+            return Ok(());
+        }
+
+        match self.paused_at.take() {
+            Some(paused_at) => {
+                self.recorder.resume()?;
+                // Time spent paused doesn't count towards max_recording_secs
+                if let Some(started) = &mut self.recording_started {
+                    *started += paused_at.elapsed();
+                }
+                self.play_feedback_if_enabled(FeedbackSoundType::Start)
+                    .await;
+                self.announce_if_enabled("Resumed").await;
+            }
+            None => {
+                self.recorder.pause()?;
+                self.paused_at = Some(Instant::now());
+                self.play_feedback_if_enabled(FeedbackSoundType::Stop).await;
+                self.announce_if_enabled("Paused").await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Step to the next entry of `languages` for the following dictations
+    async fn cycle_language(&mut self) {
+        let languages = &self.config.languages;
+        if languages.is_empty() {
+            return;
+        }
+
+        let next = self
+            .current_language()
+            .and_then(|current| languages.iter().position(|l| *l == current))
+            .map_or(0, |index| (index + 1) % languages.len());
+        let language = languages[next].clone();
+
+        tracing::info!("Switching language to {:?}", language);
+        self.play_feedback_if_enabled(FeedbackSoundType::Complete)
+            .await;
+        self.announce_if_enabled(&format!("Language {}", language))
+            .await;
+        self.language_override = Some(language);
+        self.publish_status();
+    }
+
+    async fn announce_if_enabled(&self, message: &str) {
+        if self.config.announcements {
+            announce::announce(message).await;
+        }
+    }
+
+    fn build_transcription_config(&self, settings: &ProfileSettings) -> TranscriptionConfig {
+        transcription_config(&self.config, settings, &self.context.prompt())
+    }
+
+    /// Settings for the current dictation: the active profile plus the app rule
+    /// matching the focused window, if any
+    async fn dictation_settings(&self) -> ProfileSettings {
+        let mut settings = self.config.profile_settings(self.active_profile.as_deref());
+
+        if !self.config.app_rules.is_empty()
+            && let Some(window) = focused_window::current().await
+        {
+            tracing::debug!("Focused window: {:?}", window);
+            if let Some(rule) = self.config.app_rule_for(&window) {
+                settings.apply_app_rule(rule);
+                tracing::info!(
+                    "Applying app rule for {:?} (language: {:?})",
+                    window.app_id,
+                    settings.language
+                );
+            }
+        }
+
+        if self.language_override.is_some() {
+            settings.language = self.language_override.clone();
+        }
+
+        settings
+    }
+
+    /// Transcribe with the configured backend, moving on to each of
+    /// `transcription_fallbacks` in turn when one fails or times out
+    async fn transcribe_with_fallbacks(
+        &self,
+        audio: &RecordedAudio,
+        stream: Option<StreamingSession>,
+        config: &TranscriptionConfig,
+    ) -> Result<Transcript> {
+        let primary = async {
+            match stream {
+                Some(stream) => match stream.finish().await {
+                    Ok(transcript) => Ok(transcript),
+                    // The recording was saved as well, so it can still be sent whole
+                    Err(e) if self.transcriber.retries_failed_stream() => {
+                        tracing::warn!("Streamed transcription failed, uploading instead: {:#}", e);
+                        self.transcriber.transcribe(audio, config).await
+                    }
+                    Err(e) => Err(e),
+                },
+                None => self.transcriber.transcribe(audio, config).await,
+            }
+        };
+        // Without a fallback, the backend's own timeout is all there is
+        let mut result = if self.fallbacks.is_empty() {
+            primary.await
+        } else {
+            within(Duration::from_secs(self.config.timeout), primary).await
+        };
+
+        let mut failed = self.config.backend_label();
+        for (index, fallback) in self.fallbacks.iter().enumerate() {
+            let error = match result {
+                Ok(text) => return Ok(text),
+                Err(e) => e,
+            };
+            tracing::warn!(
+                "Transcription with {} failed: {:#}; trying {}",
+                failed,
+                error,
+                fallback.label
+            );
+            announce::notify(
+                "Dictator",
+                &format!(
+                    "Transcription with {} failed; trying {}",
+                    failed, fallback.label
+                ),
+            );
+
+            let config = TranscriptionConfig {
+                model: fallback.model.clone(),
+                ..config.clone()
+            };
+            let attempt = fallback.transcriber.transcribe(audio, &config);
+            result = if index + 1 == self.fallbacks.len() {
+                attempt.await
+            } else {
+                within(fallback.timeout, attempt).await
+            };
+            failed = fallback.label.clone();
         }
+        result
     }
 
-    async fn transcribe_and_process(&self, audio_path: &std::path::Path) -> Result<String> {
+    /// Transcribe the recording and turn the text into segments to inject
+    ///
+    /// A language detected by the backend is filled into `settings`, so the
+    /// text is processed (and the dictation recorded) in that language.
+    async fn transcribe_and_process(
+        &self,
+        audio: &RecordedAudio,
+        stream: Option<StreamingSession>,
+        settings: &mut ProfileSettings,
+        latency: &mut LatencyBreakdown,
+    ) -> Result<Vec<Segment>> {
         tracing::info!("Transcribing...");
-        let transcription_config = self.build_transcription_config();
-        let text = transcription::transcribe(
-            audio_path,
-            &self.transcription_client,
-            &transcription_config,
-        )
-        .await?;
+        let transcription_config = self.build_transcription_config(settings);
+        let started = Instant::now();
+        let mut transcript = self
+            .transcribe_with_fallbacks(audio, stream, &transcription_config)
+            .instrument(tracing::debug_span!("transcription"))
+            .await?;
+        // Translations are always English, whatever was detected
+        if settings.language.is_none()
+            && !settings.translate
+            && let Some(detected) = transcript.language.clone()
+        {
+            let allowed = &self.config.allowed_languages;
+            if allowed.is_empty() || allowed.contains(&detected) {
+                tracing::info!("Detected language: {}", detected);
+                settings.language = Some(detected);
+            } else {
+                let language = allowed[0].clone();
+                tracing::info!(
+                    "Detected language {:?} is not in allowed_languages, transcribing again in {:?}",
+                    detected,
+                    language
+                );
+                let config = TranscriptionConfig {
+                    language: language.clone(),
+                    ..transcription_config
+                };
+                transcript = self
+                    .transcribe_with_fallbacks(audio, None, &config)
+                    .instrument(tracing::debug_span!("transcription"))
+                    .await?;
+                settings.language = Some(language);
+            }
+        }
+        if let Some(overrides) = settings
+            .language
+            .as_ref()
+            .and_then(|language| self.config.language_word_overrides.get(language))
+        {
+            settings.word_overrides.extend(overrides.clone());
+        }
+        for segment in &transcript.segments {
+            tracing::debug!(
+                "Segment {:.1}-{:.1}s (avg_logprob {:?}, no_speech_prob {:?}): {}",
+                segment.start,
+                segment.end,
+                segment.avg_logprob,
+                segment.no_speech_prob,
+                segment.text.trim()
+            );
+        }
+        latency.transcription_ms = history::elapsed_ms(started);
+        if transcript.is_no_speech(self.config.no_speech_threshold) {
+            tracing::info!(
+                "Dropping transcription {:?}: the model detected no speech",
+                transcript.text
+            );
+            self.report_no_speech().await;
+            return Ok(Vec::new());
+        }
+        let text = transcript.text;
         tracing::info!("Transcription: {}", text);
 
+        if let Some((phrase, command)) =
+            hooks::find_voice_command(&self.config.voice_commands, &text)
+        {
+            tracing::info!("Voice command {:?} recognized", phrase);
+            hooks::run_hook("voice_command", command);
+            return Ok(Vec::new());
+        }
+
         tracing::info!("Processing text...");
-        let processed_text = self.text_processor.process(&text);
-        tracing::info!("Processed text: {}", processed_text);
+        let started = Instant::now();
+        let segments =
+            tracing::debug_span!("processing").in_scope(|| self.process_text(&text, settings));
+        latency.processing_ms = history::elapsed_ms(started);
+        tracing::info!("Processed text: {:?}", segments);
 
-        Ok(processed_text.to_string())
+        Ok(segments)
+    }
+
+    /// Split a transcript into key commands and processed text
+    fn process_text(&self, text: &str, settings: &ProfileSettings) -> Vec<Segment> {
+        // A profile switch or app rule may change language (and so punctuation
+        // locale), overrides, regex rules, punctuation, emoji and symbol
+        // commands, snippets, terminal punctuation, number conversion,
+        // capitalization or case commands for this dictation
+        let rebuilt;
+        let text_processor = if settings.language == self.text_processor_settings.language
+            && settings.translate == self.text_processor_settings.translate
+            && settings.word_overrides == self.text_processor_settings.word_overrides
+            && settings.regex_rules == self.text_processor_settings.regex_rules
+            && settings.punctuation_commands == self.text_processor_settings.punctuation_commands
+            && settings.punctuation_overrides == self.text_processor_settings.punctuation_overrides
+            && settings.emoji_commands == self.text_processor_settings.emoji_commands
+            && settings.symbol_commands == self.text_processor_settings.symbol_commands
+            && settings.symbol_commands_stage == self.text_processor_settings.symbol_commands_stage
+            && settings.snippets == self.text_processor_settings.snippets
+            && settings.terminal_punctuation == self.text_processor_settings.terminal_punctuation
+            && settings.convert_numbers == self.text_processor_settings.convert_numbers
+            && settings.capitalize_sentences == self.text_processor_settings.capitalize_sentences
+            && settings.case_commands == self.text_processor_settings.case_commands
+            && settings.capitalization_commands
+                == self.text_processor_settings.capitalization_commands
+        {
+            &self.text_processor
+        } else {
+            rebuilt = build_text_processor(settings);
+            &rebuilt
+        };
+        // Key commands are matched on the raw transcript so they take
+        // precedence over punctuation commands with the same phrase
+        KeyCommands::new(&settings.key_commands)
+            .split(text)
+            .into_iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => Segment::Text(text_processor.process(&text)),
+                keys => keys,
+            })
+            .collect()
+    }
+
+    /// Tell the user a recording was dropped as silence rather than typed
+    async fn report_no_speech(&self) {
+        announce::notify("Dictator", "No speech detected; nothing was typed");
+        self.play_feedback_if_enabled(FeedbackSoundType::Discarded)
+            .await;
+        self.announce_if_enabled("No speech").await;
     }
 
-    async fn stop_recording_with_feedback(&mut self) -> Result<tempfile::NamedTempFile> {
+    /// Stop recording and finalize the audio, or discard it if it is shorter
+    /// than `min_recording_ms`
+    async fn stop_recording_with_feedback(
+        &mut self,
+        latency: &mut LatencyBreakdown,
+    ) -> Result<Option<RecordedAudio>> {
         tracing::info!("Stopping recording");
-        self.state = AppState::Processing;
+        self.set_state(AppState::Processing);
+        self.recording_started = None;
+        self.paused_at = None;
 
-        let temp_file = self.recorder.stop().await?;
-        tracing::info!("Recording saved to: {:?}", temp_file.path());
+        let started = Instant::now();
+        let (audio, stats) = self
+            .recorder
+            .stop()
+            .instrument(tracing::debug_span!("finalize"))
+            .await?;
+        latency.finalize_ms = history::elapsed_ms(started);
+        tracing::info!("Recording saved: {:?}", audio);
+
+        let duration = stats.duration(self.config.audio_format());
+        if duration < Duration::from_millis(self.config.min_recording_ms) {
+            tracing::info!(
+                "Discarding {:.2}s recording, shorter than min_recording_ms",
+                duration.as_secs_f32()
+            );
+            self.play_feedback_if_enabled(FeedbackSoundType::Discarded)
+                .await;
+            self.announce_if_enabled("Too short").await;
+            return Ok(None);
+        }
+
+        if let Some(threshold) = self.config.silence_threshold_dbfs
+            && stats.rms_dbfs() < threshold
+        {
+            tracing::info!(
+                "Discarding recording at {:.1} dBFS, below silence_threshold_dbfs",
+                stats.rms_dbfs()
+            );
+            self.report_no_speech().await;
+            return Ok(None);
+        }
+
+        if let Some(warning) = stats.warning() {
+            tracing::warn!("{}", warning);
+            if self.config.notify_level_warnings {
+                announce::notify("Dictator", warning);
+            }
+        }
 
         self.play_feedback_if_enabled(FeedbackSoundType::Stop).await;
+        self.announce_if_enabled("Transcribing").await;
 
-        Ok(temp_file)
+        Ok(Some(audio))
     }
 
     async fn handle_start_recording(&mut self) -> Result<()> {
         tracing::info!("Starting recording");
         tracing::debug!("handle_toggle: changing state to Recording");
-        self.state = AppState::Recording;
+        self.set_state(AppState::Recording);
+        self.dictations += 1;
 
-        self.play_feedback_if_enabled(FeedbackSoundType::Start)
-            .await;
+        if self.service_reachable() {
+            self.play_feedback_if_enabled(FeedbackSoundType::Start)
+                .await;
+            self.announce_if_enabled("Recording").await;
+        } else {
+            tracing::warn!("Recording while the transcription service is unreachable");
+            self.play_feedback_if_enabled(FeedbackSoundType::Unavailable)
+                .await;
+            self.announce_if_enabled("Recording, service unreachable")
+                .await;
+        }
+
+        if self.config.warm_up_on_record {
+            self.transcriber.warm_up();
+        }
+
+        let settings = self.dictation_settings().await;
+        self.recorder.set_audio_source(settings.audio_source)?;
 
         tracing::debug!("handle_toggle: calling recorder.start()");
-        self.recorder.start()?;
+        let stream = self
+            .transcriber
+            .start_stream(&self.build_transcription_config(&settings));
+        self.recorder
+            .start(stream.as_ref().map(StreamingSession::tap))?;
+        self.stream = stream;
         tracing::debug!("handle_toggle: recorder.start() completed");
+        self.recording_started = Some(Instant::now());
+        self.paused_at = None;
+        self.limit_warned = false;
 
         self.run_hook_if_configured("on_recording_start", &self.config.on_recording_start.clone());
 
@@ -151,30 +1152,455 @@ impl App {
     }
 
     async fn handle_stop_and_process(&mut self) -> Result<()> {
-        let temp_file = self.stop_recording_with_feedback().await?;
+        let stop_pressed = Instant::now();
+        let mut latency = LatencyBreakdown::default();
+        let Some(audio) = self.stop_recording_with_feedback(&mut latency).await? else {
+            self.set_state(AppState::Idle);
+            self.run_hook_if_configured(
+                "on_recording_stop",
+                &self.config.on_recording_stop.clone(),
+            );
+            return Ok(());
+        };
 
-        // Perform transcription and text injection
         // We capture the result so we can reset state regardless of success/failure
-        let result = async {
-            let processed_text = self.transcribe_and_process(temp_file.path()).await?;
+        let stream = self.stream.take();
+        let result = self
+            .process_recording(&audio, stream, stop_pressed, latency)
+            .await;
+        let result = match result {
+            Err(e)
+                if self.config.offline_queue
+                    && self.transcriber.check_availability().await.is_err() =>
+            {
+                tracing::warn!("Transcription service unreachable: {:#}", e);
+                self.enqueue_offline(audio).await;
+                Ok(None)
+            }
+            result => {
+                match &result {
+                    // The refinement hands the recording back when it is done
+                    Ok(Some(draft)) if self.config.refine_model.is_some() => {
+                        self.last_recording = None;
+                        self.refine(audio, draft.clone());
+                    }
+                    _ if self.config.keep_last_recording => self.last_recording = Some(audio),
+                    _ => {}
+                }
+                result
+            }
+        };
+
+        // Always reset state to Idle, even if transcription or injection failed
+        self.set_state(AppState::Idle);
+
+        self.run_hook_if_configured("on_recording_stop", &self.config.on_recording_stop.clone());
+
+        result.map(|_| ())
+    }
+
+    /// Report a change found by the background health check
+    fn handle_health(&mut self, reachable: bool) {
+        if reachable {
+            tracing::info!("Transcription service is reachable again");
+            // No need to wait for the next offline retry
+            if !self.offline_queue.is_empty() {
+                self.offline_retry_at = Some(Instant::now());
+            }
+        } else {
+            tracing::warn!("Transcription service is unreachable");
+        }
+        self.publish_status();
+    }
+
+    /// Keep a recording the service couldn't be reached for, to transcribe
+    /// when `retry_offline_queue` finds it back
+    async fn enqueue_offline(&mut self, audio: RecordedAudio) {
+        let settings = self.dictation_settings().await;
+        let saved = self.offline_store.as_ref().and_then(|store| {
+            let dictation = QueuedDictation {
+                timestamp: offline_queue::now_ms(),
+                profile: self.active_profile.clone(),
+                language: settings.language.clone(),
+            };
+            store
+                .save(&audio, &dictation)
+                .inspect_err(|e| tracing::warn!("Failed to save the queued recording: {:#}", e))
+                .ok()
+        });
+        self.offline_queue.push_back(Queued {
+            audio,
+            settings,
+            saved,
+        });
+        tracing::info!(
+            "Queued the recording until the service is back ({} waiting)",
+            self.offline_queue.len()
+        );
+        if self.offline_retry_at.is_none() {
+            self.offline_retry_at = Some(Instant::now() + self.offline_retry_interval());
+        }
+        self.publish_status();
+
+        announce::notify(
+            "Dictator",
+            "Transcription service unreachable; the recording will be transcribed when it is back",
+        );
+        self.play_feedback_if_enabled(FeedbackSoundType::Discarded)
+            .await;
+        self.announce_if_enabled("Saved for later").await;
+    }
+
+    fn offline_retry_interval(&self) -> Duration {
+        Duration::from_secs(self.config.offline_retry_secs)
+    }
+
+    /// Transcribe the queued recordings if the service answers again, and
+    /// deliver each transcript as `offline_action` says
+    async fn retry_offline_queue(&mut self) {
+        self.offline_retry_at = Some(Instant::now() + self.offline_retry_interval());
+        // Typing into a dictation in progress would garble both
+        if self.state != AppState::Idle {
+            return;
+        }
+        if let Err(e) = self.transcriber.check_availability().await {
+            tracing::debug!("Transcription service still unreachable: {:#}", e);
+            return;
+        }
+
+        tracing::info!(
+            "Transcription service is back, transcribing {} queued recordings",
+            self.offline_queue.len()
+        );
+        while let Some(Queued {
+            audio,
+            mut settings,
+            saved,
+        }) = self.offline_queue.pop_front()
+        {
+            let mut latency = LatencyBreakdown::default();
+            let segments = match self
+                .transcribe_and_process(&audio, None, &mut settings, &mut latency)
+                .await
+            {
+                Ok(segments) => segments,
+                Err(e) => {
+                    tracing::warn!("Transcribing a queued recording failed: {:#}", e);
+                    self.offline_queue.push_front(Queued {
+                        audio,
+                        settings,
+                        saved,
+                    });
+                    break;
+                }
+            };
+            if let Err(e) = self.deliver_queued(segments).await {
+                tracing::warn!("Failed to deliver a queued transcript: {:#}", e);
+            }
+            if let (Some(store), Some(path)) = (&self.offline_store, &saved)
+                && let Err(e) = store.remove(path)
+            {
+                tracing::warn!("{:#}", e);
+            }
+        }
+
+        if self.offline_queue.is_empty() {
+            self.offline_retry_at = None;
+        }
+        self.publish_status();
+    }
+
+    /// Copy or type the transcript of a queued recording, with a notification
+    async fn deliver_queued(&mut self, segments: Vec<Segment>) -> Result<()> {
+        let text = segments_text(&segments);
+        if text.is_empty() {
+            return Ok(());
+        }
+        match self.config.offline_action {
+            OfflineAction::Clipboard => {
+                text_injection::inject_text(text, &PasteMode::None).await?;
+                announce::notify("Dictator", "Queued transcript copied to clipboard");
+            }
+            OfflineAction::Type => {
+                self.inject(segments).await?;
+                self.last_injected = Some(text);
+                self.scratchable = None;
+                announce::notify("Dictator", "Queued transcript typed");
+            }
+        }
+        Ok(())
+    }
+
+    /// Transcribe the recording again with `refine_model` in the background;
+    /// the result arrives at `handle_refined`
+    fn refine(&self, audio: RecordedAudio, draft: Draft) {
+        let config = TranscriptionConfig {
+            model: self.config.refine_model.clone().unwrap_or_default(),
+            ..self.build_transcription_config(&draft.settings)
+        };
+        let transcriber = self.transcriber.clone();
+        let refined_tx = self.refined_tx.clone();
+        let dictation = self.dictations;
+        tokio::task::spawn_local(async move {
+            tracing::info!("Refining the draft with {}", config.model);
+            let result = transcriber.transcribe(&audio, &config).await;
+            let _ = refined_tx.send(Refined {
+                dictation,
+                audio,
+                draft,
+                result,
+            });
+        });
+    }
+
+    /// Copy the refined transcript, or type it in place of the draft
+    async fn handle_refined(&mut self, refined: Refined) {
+        let Refined {
+            dictation,
+            audio,
+            draft,
+            result,
+        } = refined;
+        // Anything dictated since has moved the cursor on from the draft
+        let latest = dictation == self.dictations && self.state == AppState::Idle;
+        if latest && self.config.keep_last_recording {
+            self.last_recording = Some(audio);
+        }
+
+        let transcript = match result {
+            Ok(transcript) => transcript,
+            Err(e) => {
+                tracing::warn!("Refining the draft failed: {:#}", e);
+                return;
+            }
+        };
+        let text = segments_text(&self.process_text(&transcript.text, &draft.settings));
+        if text.is_empty() || text == draft.text {
+            tracing::info!("The refined transcript matches the draft");
+            return;
+        }
+        tracing::info!("Refined transcript: {:?}", text);
+
+        let replace = self.config.refine_action == RefineAction::Replace
+            && latest
+            && !draft.pressed_keys
+            && self.last_injected.as_ref() == Some(&draft.text);
+        let result = if replace {
+            self.replace_draft(&draft.text, text).await
+        } else {
+            text_injection::inject_text(text, &PasteMode::None)
+                .await
+                .map(|()| announce::notify("Dictator", "Refined transcript copied to clipboard"))
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to apply the refined transcript: {:#}", e);
+        }
+    }
+
+    /// Delete the draft just typed and type `text` in its place
+    async fn replace_draft(&mut self, draft: &str, text: String) -> Result<()> {
+        tracing::info!("Replacing the draft with the refined transcript");
+        text_injection::delete_chars(text_injection::typed_len(draft)).await?;
+        text_injection::inject_text(text.clone(), &self.config.paste_mode).await?;
+        self.scratchable = Some(text.clone());
+        self.last_injected = Some(text);
+        Ok(())
+    }
+
+    /// Drop a scratch command that starts the dictation and, with
+    /// `scratch_undo_injection`, delete what the previous dictation typed
+    ///
+    /// A scratch command after other text was already handled by the text
+    /// processor, which deletes the sentence before it.
+    async fn scratch_previous(&mut self, segments: &mut Vec<Segment>) -> Result<()> {
+        let Some(scratch) = &self.scratch else {
+            return Ok(());
+        };
+        let Some(Segment::Text(first)) = segments.first() else {
+            return Ok(());
+        };
+        let Some(rest) = scratch.strip_leading(first).map(str::to_string) else {
+            return Ok(());
+        };
+        if rest.is_empty() {
+            segments.remove(0);
+        } else {
+            segments[0] = Segment::Text(rest);
+        }
+
+        if !self.config.scratch_undo_injection {
+            return Ok(());
+        }
+        match self.scratchable.take() {
+            Some(typed) => {
+                tracing::info!("Scratching the previous dictation");
+                text_injection::delete_chars(text_injection::typed_len(&typed)).await?;
+                self.last_injected = None;
+            }
+            None => tracing::info!("No typed dictation to scratch"),
+        }
+        Ok(())
+    }
+
+    /// Run the last recording through transcription, processing and injection again
+    async fn handle_retry(&mut self) -> Result<()> {
+        if self.state != AppState::Idle {
+            tracing::debug!("Retry pressed while {:?}, ignoring", self.state);
+            return Ok(());
+        }
+        let Some(audio) = self.last_recording.take() else {
+            tracing::info!("No recording to retry");
+            self.announce_if_enabled("Nothing to retry").await;
+            return Ok(());
+        };
+
+        tracing::info!("Retrying last recording: {:?}", audio);
+        self.set_state(AppState::Processing);
+        self.announce_if_enabled("Transcribing").await;
+        let result = self
+            .process_recording(&audio, None, Instant::now(), LatencyBreakdown::default())
+            .await;
+        self.last_recording = Some(audio);
+        self.set_state(AppState::Idle);
+        result.map(|_| ())
+    }
+
+    /// Transcribe a recording, then process and inject the text
+    ///
+    /// A streaming backend's `stream` already has the text; otherwise the
+    /// recording is transcribed now. Cancelling drops the result instead of
+    /// injecting it.
+    async fn process_recording(
+        &mut self,
+        audio: &RecordedAudio,
+        stream: Option<StreamingSession>,
+        stop_pressed: Instant,
+        mut latency: LatencyBreakdown,
+    ) -> Result<Option<Draft>> {
+        let cancel = self.cancel.clone();
+        let pipeline = async {
+            let mut settings = self.dictation_settings().await;
+            let mut segments = self
+                .transcribe_and_process(audio, stream, &mut settings, &mut latency)
+                .await?;
+
+            if let Some(archive) = &self.archive {
+                match archive.save(audio, &segments_text(&segments)) {
+                    Ok(path) => tracing::debug!("Archived recording to {:?}", path),
+                    Err(e) => tracing::warn!("Failed to archive recording: {:#}", e),
+                }
+            }
+
+            // Compose mode drops the previous dictation with its own phrase
+            if !self.config.compose_mode {
+                self.scratch_previous(&mut segments).await?;
+            }
+
+            if self.config.compose_mode {
+                match self.compose.add(segments) {
+                    Some(buffered) => segments = buffered,
+                    None => {
+                        // Still collecting; nothing is injected until the send phrase
+                        compose::show_preview(&self.compose);
+                        self.announce_if_enabled("Added to draft").await;
+                        return Ok(None);
+                    }
+                }
+            }
+
+            let chars = segments
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Text(text) => text.chars().count(),
+                    Segment::Keys(_) => 0,
+                })
+                .sum();
+
+            // Nothing to follow up on after a voice command or an empty transcript
+            if !segments.is_empty()
+                && let Some(keys) = &settings.after_injection_keys
+            {
+                segments.push(Segment::Keys(keys.clone()));
+            }
+
+            let injected_text = segments_text(&segments);
+            let pressed_keys = segments
+                .iter()
+                .any(|segment| matches!(segment, Segment::Keys(_)));
 
             tracing::info!("Injecting text...");
-            text_injection::inject_text(processed_text, &self.config.paste_mode).await?;
+            let started = Instant::now();
+            self.inject(segments)
+                .instrument(tracing::debug_span!("injection"))
+                .await?;
+            latency.injection_ms = history::elapsed_ms(started);
+            if !injected_text.is_empty() {
+                self.last_injected = Some(injected_text.clone());
+            }
+            self.scratchable =
+                (!pressed_keys && !injected_text.is_empty()).then(|| injected_text.clone());
+            if self.config.context_prompt {
+                self.context.push(&injected_text);
+            }
+            latency.total_ms = history::elapsed_ms(stop_pressed);
 
             self.play_feedback_if_enabled(FeedbackSoundType::Complete)
                 .await;
+            self.announce_if_enabled("Done").await;
 
-            tracing::info!("Complete!");
-            Ok(())
+            tracing::info!(
+                chars,
+                finalize_ms = latency.finalize_ms,
+                transcription_ms = latency.transcription_ms,
+                processing_ms = latency.processing_ms,
+                injection_ms = latency.injection_ms,
+                total_ms = latency.total_ms,
+                "Complete!"
+            );
+            self.record_history(chars, settings.language.clone(), latency);
+            Ok((!injected_text.is_empty()).then_some(Draft {
+                text: injected_text,
+                pressed_keys,
+                settings,
+            }))
+        };
+        tokio::select! {
+            result = pipeline => result,
+            _ = cancel.notified() => {
+                tracing::info!("Processing cancelled, discarding the result");
+                Ok(None)
+            }
         }
-        .await;
+    }
 
-        // Always reset state to Idle, even if transcription or injection failed
-        self.state = AppState::Idle;
+    async fn inject(&mut self, segments: Vec<Segment>) -> Result<()> {
+        if self.config.paste_mode != PasteMode::Portal {
+            return text_injection::inject_segments(
+                segments,
+                &self.config.paste_mode,
+                &self.config.append_separator,
+            )
+            .await;
+        }
 
-        self.run_hook_if_configured("on_recording_stop", &self.config.on_recording_stop.clone());
+        // The portal may show a permission dialog, so connect on first use
+        // rather than at startup
+        let keyboard = match &mut self.portal_keyboard {
+            Some(keyboard) => keyboard,
+            keyboard => keyboard.insert(PortalKeyboard::connect().await?),
+        };
+        keyboard.inject_segments(segments).await
+    }
 
-        result
+    fn record_history(&self, chars: usize, language: Option<String>, latency: LatencyBreakdown) {
+        if !self.config.history_enabled {
+            return;
+        }
+
+        let entry = HistoryEntry::new(chars, self.active_profile.clone(), language, latency);
+        if let Err(e) = history::append(&entry) {
+            tracing::warn!("Failed to record history: {}", e);
+        }
     }
 
     fn run_hook_if_configured(&self, label: &str, command: &Option<String>) {
@@ -183,18 +1609,128 @@ impl App {
         }
     }
 
-    fn setup_audio_pipeline() -> Recorder {
-        let format = AudioFormat::default();
-        Recorder::new(format)
+    /// Create the recorder without touching the audio host; cpal is only
+    /// initialized when a recording starts and released when it stops
+    /// (unless `pre_roll_ms` asks for the microphone to stay open).
+    fn setup_audio_pipeline(config: &Config) -> Recorder {
+        let format = config.audio_format();
+        let options = RecorderOptions {
+            upload_format: config.upload_format,
+            in_memory: config.audio_in_memory,
+            input_device: config.input_device.clone(),
+            extra_input_devices: config.extra_input_devices.clone(),
+            input_mix: config.input_mix,
+            audio_source: config
+                .profile_settings(config.active_profile.as_deref())
+                .audio_source,
+            monitor_device: config.monitor_device.clone(),
+            input_channel: config.input_channel,
+            jack: (config.audio_host == AudioHost::Jack).then(|| JackOptions {
+                client_name: config.jack_client_name.clone(),
+                auto_connect: config.jack_auto_connect,
+            }),
+            auto_gain_target_dbfs: config.auto_gain_target_dbfs,
+            pre_roll_ms: config.pre_roll_ms,
+            buffers: config.capture_buffers(),
+            temp_dir: config.temp_dir.clone(),
+        };
+        Recorder::new(format, options)
     }
 
-    fn setup_keyboard_monitoring() -> Result<mpsc::Receiver<()>> {
+    /// Start listening for shortcuts
+    ///
+    /// The portal session is re-established if it fails or ends, e.g. when
+    /// xdg-desktop-portal restarts.
+    ///
+    /// Cancel presses are signalled on `cancel` rather than the returned channel,
+    /// so they reach the pipeline while it is busy transcribing and not reading
+    /// the channel. `levels` is streamed to `dictator level` clients and
+    /// `status` answers `dictator status`.
+    fn setup_keyboard_monitoring(
+        config: &Config,
+        cancel: Arc<Notify>,
+        levels: watch::Receiver<InputLevel>,
+        status: watch::Receiver<Status>,
+    ) -> Result<mpsc::Receiver<ShortcutEvent>> {
+        let (monitor_tx, mut monitor_rx) = mpsc::channel(10);
         let (shortcut_tx, shortcut_rx) = mpsc::channel(10);
+
+        let control_tx = monitor_tx.clone();
+        tokio::spawn(monitor_shortcuts_with_retry(config.clone(), monitor_tx));
+
+        let profiles = config.profiles.keys().cloned().collect();
+        tokio::spawn(async move {
+            if let Err(e) = control::listen(control_tx, levels, status, profiles).await {
+                tracing::warn!("Control socket unavailable: {}", e);
+            }
+        });
+
         tokio::spawn(async move {
-            if let Err(e) = shortcuts::monitor_shortcut(shortcut_tx).await {
-                tracing::error!("Shortcut monitoring error: {}", e);
+            while let Some(event) = monitor_rx.recv().await {
+                if event == ShortcutEvent::Cancel {
+                    cancel.notify_one();
+                } else if shortcut_tx.send(event).await.is_err() {
+                    break;
+                }
             }
         });
+
         Ok(shortcut_rx)
     }
 }
+
+/// Delay before re-registering shortcuts after the portal session fails
+const MONITOR_RETRY_INITIAL: Duration = Duration::from_secs(1);
+const MONITOR_RETRY_MAX: Duration = Duration::from_secs(60);
+
+/// Consecutive failures after which the user is told the shortcut is down
+const MONITOR_FAILURES_BEFORE_ALERT: u32 = 3;
+
+/// Keep the shortcut portal session alive, re-registering with backoff
+///
+/// A session that lasted longer than the maximum backoff counts as healthy,
+/// so a later failure starts over from the initial delay.
+async fn monitor_shortcuts_with_retry(config: Config, tx: mpsc::Sender<ShortcutEvent>) {
+    let mut delay = MONITOR_RETRY_INITIAL;
+    let mut failures = 0;
+
+    loop {
+        let started = Instant::now();
+        let result = shortcuts::monitor_shortcut(
+            config.shortcut.clone(),
+            config.correction_shortcut.clone(),
+            config.cancel_shortcut.clone(),
+            config.shortcut_bindings.clone(),
+            tx.clone(),
+        )
+        .await;
+        if tx.is_closed() {
+            return;
+        }
+
+        if started.elapsed() > MONITOR_RETRY_MAX {
+            delay = MONITOR_RETRY_INITIAL;
+            failures = 0;
+        }
+        failures += 1;
+        match result {
+            Ok(()) => tracing::warn!(
+                "Shortcut portal session ended; re-registering in {:?}",
+                delay
+            ),
+            Err(e) => tracing::warn!("Shortcut monitoring error: {}; retrying in {:?}", e, delay),
+        }
+        if failures == MONITOR_FAILURES_BEFORE_ALERT {
+            tracing::error!(
+                "Global shortcut unavailable after {} attempts; still retrying",
+                failures
+            );
+            if config.announcements {
+                announce::announce("Shortcut unavailable").await;
+            }
+        }
+
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MONITOR_RETRY_MAX);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::audio::RecordedAudio;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Prefix of every file dictator writes to the archive directory
+const PREFIX: &str = "dictation-";
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Keeps a copy of each recording and its transcript (`archive_dir`)
+///
+/// Each dictation is stored as `dictation-<UTC timestamp>.<ext>` plus a `.txt`
+/// with the same name, so the files sort chronologically.
+pub struct Archive {
+    dir: PathBuf,
+    /// Recordings to keep; 0 keeps all
+    max_files: usize,
+    /// Days to keep recordings for; 0 keeps them forever
+    max_age_days: u64,
+}
+
+impl Archive {
+    pub fn new(dir: PathBuf, max_files: usize, max_age_days: u64) -> Self {
+        Self {
+            dir,
+            max_files,
+            max_age_days,
+        }
+    }
+
+    /// Store a recording and its transcript, returning the recording's path
+    pub fn save(&self, audio: &RecordedAudio, transcript: &str) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create archive directory: {:?}", self.dir))?;
+
+        // The timestamp contains a dot, so names are built rather than using
+        // `with_extension`, which would take the milliseconds for an extension
+        let stem = format!("{}{}", PREFIX, timestamp(SystemTime::now()));
+        let path = match audio {
+            RecordedAudio::File(file) => {
+                let extension = file.path().extension().unwrap_or_default();
+                let path = self
+                    .dir
+                    .join(format!("{}.{}", stem, extension.to_string_lossy()));
+                std::fs::copy(file.path(), &path)
+                    .with_context(|| format!("Failed to archive recording to {:?}", path))?;
+                path
+            }
+            RecordedAudio::Memory(bytes) => {
+                let path = self.dir.join(format!("{}.wav", stem));
+                std::fs::write(&path, bytes)
+                    .with_context(|| format!("Failed to archive recording to {:?}", path))?;
+                path
+            }
+        };
+
+        let transcript_path = self.dir.join(format!("{}.txt", stem));
+        std::fs::write(&transcript_path, format!("{}\n", transcript))
+            .with_context(|| format!("Failed to archive transcript to {:?}", transcript_path))?;
+        Ok(path)
+    }
+
+    /// Delete recordings beyond `max_files` or older than `max_age_days`,
+    /// along with their transcripts. Returns how many dictations were removed.
+    pub fn prune(&self) -> Result<usize> {
+        if self.max_files == 0 && self.max_age_days == 0 {
+            return Ok(0);
+        }
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+
+        // Group the files of each dictation by their shared name
+        let mut dictations: Vec<(String, Vec<PathBuf>, SystemTime)> = Vec::new();
+        let entries = std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read archive directory: {:?}", self.dir))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(stem) = archived_stem(&path) else {
+                continue;
+            };
+            let modified = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(UNIX_EPOCH);
+            match dictations.iter_mut().find(|(name, _, _)| *name == stem) {
+                Some((_, files, newest)) => {
+                    files.push(path);
+                    *newest = (*newest).max(modified);
+                }
+                None => dictations.push((stem, vec![path], modified)),
+            }
+        }
+        dictations.sort_by(|a, b| b.0.cmp(&a.0));
+
+        let max_age = Duration::from_secs(self.max_age_days * SECONDS_PER_DAY);
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for (index, (_, files, modified)) in dictations.iter().enumerate() {
+            let too_many = self.max_files > 0 && index >= self.max_files;
+            let too_old = self.max_age_days > 0
+                && now.duration_since(*modified).unwrap_or_default() > max_age;
+            if !too_many && !too_old {
+                continue;
+            }
+
+            for file in files {
+                std::fs::remove_file(file)
+                    .with_context(|| format!("Failed to delete archived file {:?}", file))?;
+            }
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+/// The shared file name of an archived recording or transcript
+fn archived_stem(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    (stem.starts_with(PREFIX) && path.is_file()).then(|| stem.to_string())
+}
+
+/// `YYYYMMDDTHHMMSS.mmmZ` in UTC, unique for dictations a millisecond apart
+fn timestamp(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time)
+        .format("%Y%m%dT%H%M%S%.3fZ")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_retention() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_792_160_581_042);
+        assert_eq!(timestamp(time), "20261016T142301.042Z");
+
+        let dir = tempfile::tempdir().unwrap();
+        let archive = Archive::new(dir.path().to_path_buf(), 2, 0);
+        for stem in ["a", "b", "c"] {
+            let name = format!("{}{}", PREFIX, stem);
+            std::fs::write(dir.path().join(format!("{}.wav", name)), b"").unwrap();
+            std::fs::write(dir.path().join(format!("{}.txt", name)), b"").unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), b"").unwrap();
+
+        assert_eq!(archive.prune().unwrap(), 1);
+        assert!(!dir.path().join("dictation-a.wav").exists());
+        assert!(!dir.path().join("dictation-a.txt").exists());
+        assert!(dir.path().join("dictation-c.txt").exists());
+        assert!(dir.path().join("notes.txt").exists());
+    }
+}
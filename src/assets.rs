@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+
+/// Directories searched for relative asset paths, in priority order:
+///
+/// 1. `assets_dirs` from the config
+/// 2. `$XDG_DATA_HOME/dictator/assets` (default `~/.local/share/dictator/assets`)
+/// 3. `<dir>/dictator/assets` for each entry of `$XDG_DATA_DIRS`
+///    (default `/usr/local/share:/usr/share`)
+/// 4. `assets/` next to the executable and `../share/dictator/assets` relative to it
+/// 5. `assets/` in the working directory, for running from a source checkout
+pub fn search_dirs(configured: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = configured.to_vec();
+
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    if let Some(data_home) = data_home {
+        dirs.push(data_home.join("dictator").join("assets"));
+    }
+
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(
+        data_dirs
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| PathBuf::from(dir).join("dictator").join("assets")),
+    );
+
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        dirs.push(exe_dir.join("assets"));
+        dirs.push(exe_dir.join("../share/dictator/assets"));
+    }
+
+    dirs.push(PathBuf::from("assets"));
+    dirs
+}
+
+/// Resolve an asset path: absolute paths are returned as-is if they exist,
+/// relative paths are looked up in each of the search directories.
+pub fn resolve(path: &Path, search_dirs: &[PathBuf]) -> Option<PathBuf> {
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    search_dirs
+        .iter()
+        .map(|dir| dir.join(path))
+        .find(|candidate| candidate.is_file())
+}
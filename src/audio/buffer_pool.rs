@@ -0,0 +1,106 @@
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+/// Pool of reusable sample buffers shared by capture, recorder and sinks
+///
+/// Chunks taken from the pool travel through the capture → recorder → sink
+/// channels and are handed back automatically when the sink drops them, so a
+/// long recording reuses a handful of allocations instead of making a new one
+/// every chunk.
+#[derive(Clone)]
+pub struct BufferPool {
+    free: Arc<Mutex<Vec<Vec<f32>>>>,
+    buffer_len: usize,
+    max_pooled: usize,
+}
+
+impl BufferPool {
+    /// Create a pool of buffers able to hold `buffer_len` samples without
+    /// reallocating, keeping at most `max_pooled` idle buffers around
+    pub fn new(buffer_len: usize, max_pooled: usize) -> Self {
+        Self {
+            free: Arc::new(Mutex::new(Vec::with_capacity(max_pooled))),
+            buffer_len,
+            max_pooled,
+        }
+    }
+
+    /// Take an empty buffer from the pool, allocating only if none is free
+    pub fn take(&self) -> AudioChunk {
+        let samples = self
+            .free
+            .lock()
+            .ok()
+            .and_then(|mut free| free.pop())
+            .unwrap_or_else(|| Vec::with_capacity(self.buffer_len));
+
+        AudioChunk {
+            samples,
+            pool: self.clone(),
+        }
+    }
+
+    fn give_back(&self, mut samples: Vec<f32>) {
+        samples.clear();
+        if let Ok(mut free) = self.free.lock()
+            && free.len() < self.max_pooled
+        {
+            free.push(samples);
+        }
+    }
+}
+
+/// A buffer of f32 samples that returns to its `BufferPool` when dropped
+pub struct AudioChunk {
+    samples: Vec<f32>,
+    pool: BufferPool,
+}
+
+impl AudioChunk {
+    /// Mutable access to the underlying buffer for filling it
+    pub fn as_mut_vec(&mut self) -> &mut Vec<f32> {
+        &mut self.samples
+    }
+}
+
+impl Deref for AudioChunk {
+    type Target = [f32];
+
+    fn deref(&self) -> &[f32] {
+        &self.samples
+    }
+}
+
+impl Drop for AudioChunk {
+    fn drop(&mut self) {
+        self.pool.give_back(std::mem::take(&mut self.samples));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_reused() {
+        let pool = BufferPool::new(1024, 2);
+
+        let mut chunk = pool.take();
+        chunk.as_mut_vec().extend_from_slice(&[0.5; 1024]);
+        let ptr = chunk.as_ptr();
+        drop(chunk);
+
+        let chunk = pool.take();
+        assert!(chunk.is_empty());
+        assert_eq!(chunk.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_pool_is_bounded() {
+        let pool = BufferPool::new(16, 1);
+        let chunks: Vec<_> = (0..3).map(|_| pool.take()).collect();
+        drop(chunks);
+
+        assert_eq!(pool.free.lock().unwrap().len(), 1);
+    }
+}
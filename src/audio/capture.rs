@@ -1,78 +1,623 @@
-use super::format::AudioFormat;
+use super::buffer_pool::{AudioChunk, BufferPool};
+use super::format::{AudioFormat, InputChannel};
+use super::level::InputLevel;
+use super::mixer::{InputMix, InputMixer};
 use anyhow::{Context, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{BufferSize, SampleRate, StreamConfig};
-use ringbuf::{HeapRb, traits::*};
+use ringbuf::{HeapProd, HeapRb, traits::*};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::sync::Arc;
-use tokio::sync::{Notify, mpsc};
+use std::time::Duration;
+use tokio::sync::{Notify, mpsc, watch};
+
+/// Default duration of each chunk handed from the capture ring buffer to the recorder
+pub const DEFAULT_CHUNK_SECONDS: f32 = 0.5;
+
+/// Default capacity of the capture ring buffer
+pub const DEFAULT_RING_SECONDS: f32 = 60.0;
+
+/// How often the input level is published while recording
+const LEVEL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Length of the blocks `InputMix::Loudest` chooses between
+const LOUDEST_BLOCK_SECONDS: f32 = 0.02;
+
+/// How many chunks one device may fall behind the others before it is
+/// treated as stalled and read as silence
+const STALL_CHUNKS: usize = 2;
+
+/// Stream properties for the PipeWire ALSA plugin (`PIPEWIRE_PROPS`)
+const PIPEWIRE_PROPS: &str = "{ application.name = \"Dictator\" application.id = \"dictator\" \
+     application.icon_name = \"audio-input-microphone\" node.name = \"dictator\" \
+     node.description = \"Dictator\" media.name = \"Dictation\" media.role = \"Communication\" }";
+
+/// The same properties for the PulseAudio ALSA plugin (`PULSE_PROP`)
+const PULSE_PROP: &str = "application.name='Dictator' application.id='dictator' \
+     application.icon_name='audio-input-microphone' media.name='Dictation' \
+     media.role='phone'";
+
+/// Name the capture stream so desktops show who is using the microphone
+///
+/// cpal talks to the sound server through ALSA, whose PipeWire and PulseAudio
+/// plugins read stream properties from the environment. With an application
+/// name set, GNOME and KDE show their "microphone in use" indicator labelled
+/// "Dictator" while a recording is open. Values already set by the user are
+/// left alone.
+///
+/// # Safety
+///
+/// Must be called while the process is still single-threaded, since it
+/// modifies the environment.
+pub unsafe fn set_stream_properties() {
+    for (name, value) in [
+        ("PIPEWIRE_PROPS", PIPEWIRE_PROPS),
+        ("PULSE_PROP", PULSE_PROP),
+    ] {
+        if std::env::var_os(name).is_none() {
+            // SAFETY: the caller guarantees no other threads are running
+            unsafe { std::env::set_var(name, value) };
+        }
+    }
+}
 
 pub struct AudioCapture;
 
+/// Audio system to capture from (`audio_host`)
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioHost {
+    /// ALSA, which reaches PipeWire and PulseAudio through their plugins
+    #[default]
+    Default,
+    /// A JACK client; needs the `jack` cargo feature
+    Jack,
+}
+
+/// What to record (`audio_source`)
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioSource {
+    /// The microphone, plus any `extra_input_devices`
+    #[default]
+    Microphone,
+    /// What is playing on the speakers, e.g. the other side of a call
+    Monitor,
+    /// The microphone and the monitor mixed together, for both sides of a call
+    Both,
+}
+
+/// Name substring that identifies a monitor source when `monitor_device` is unset
+const MONITOR_NAME: &str = "monitor";
+
+fn is_monitor(name: &str) -> bool {
+    name.to_lowercase().contains(MONITOR_NAME)
+}
+
+/// How to appear in a JACK session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JackOptions {
+    /// Client name shown in patchbays
+    pub client_name: String,
+    /// Connect the input ports to the system capture ports
+    pub auto_connect: bool,
+}
+
+/// Which input device to capture from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeviceSelection<'a> {
+    /// The configured `input_device`, as a case-insensitive name substring
+    pub preferred: Option<&'a str>,
+    /// A device that just failed, skipped when picking a replacement
+    pub failed: Option<&'a str>,
+    /// Which of the device's channels to record
+    pub channel: InputChannel,
+    /// Capture as a JACK client instead of from an ALSA device
+    pub jack: Option<&'a JackOptions>,
+    /// Further devices recorded alongside the main one (`extra_input_devices`)
+    pub extra: &'a [String],
+    /// How the devices are combined when there is more than one
+    pub mix: InputMix,
+    /// Whether to record the microphone, the monitor or both
+    pub source: AudioSource,
+    /// The configured `monitor_device`, as a case-insensitive name substring
+    pub monitor: Option<&'a str>,
+}
+
+/// Sizes of the buffers between the audio callback and the recorder
+///
+/// Smaller chunks reach the recorder sooner; a larger ring buffer rides out
+/// longer stalls of the recording task without dropping audio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureBuffers {
+    /// Audio forwarded to the recorder at a time (`chunk_ms`)
+    pub chunk_seconds: f32,
+    /// Capacity of each device's ring buffer (`ring_buffer_secs`)
+    pub ring_seconds: f32,
+}
+
+impl Default for CaptureBuffers {
+    fn default() -> Self {
+        Self {
+            chunk_seconds: DEFAULT_CHUNK_SECONDS,
+            ring_seconds: DEFAULT_RING_SECONDS,
+        }
+    }
+}
+
+/// Where a capture stream reports on itself
+#[derive(Clone)]
+pub struct CaptureEvents {
+    /// Receives the device's name if it disappears
+    pub device_lost: mpsc::UnboundedSender<String>,
+    /// Updated with the input level while recording
+    pub level: watch::Sender<InputLevel>,
+}
+
+/// Running input streams; capture stops when this is dropped
+pub struct CaptureStream {
+    _streams: Vec<cpal::Stream>,
+    /// Name of the main device being captured from
+    pub device: String,
+}
+
+/// A capture stream kept open between recordings (`pre_roll_ms`)
+///
+/// While idle the most recent audio is kept in memory and discarded as it
+/// ages out; `record` hands it to the new recording ahead of the live audio.
+/// Dropping this closes the stream.
+pub struct PreRollCapture {
+    stream: CaptureStream,
+    sessions: mpsc::UnboundedSender<mpsc::Sender<AudioChunk>>,
+}
+
+impl PreRollCapture {
+    /// Name of the device being captured from
+    pub fn device(&self) -> &str {
+        &self.stream.device
+    }
+
+    /// Send the buffered pre-roll and then live chunks to `chunk_tx` until it closes
+    pub fn record(&self, chunk_tx: mpsc::Sender<AudioChunk>) -> Result<()> {
+        self.sessions
+            .send(chunk_tx)
+            .map_err(|_| anyhow::anyhow!("Pre-roll capture has stopped"))
+    }
+}
+
 impl AudioCapture {
     /// Start audio capture
     ///
     /// Returns the stream which must be kept alive for audio capture to continue.
-    /// Audio chunks are sent via chunk_tx.
-    pub fn start(format: AudioFormat, chunk_tx: mpsc::Sender<Vec<f32>>) -> Result<cpal::Stream> {
-        let ring = HeapRb::<f32>::new(format.samples_for_duration(60.0));
-        let (mut producer, consumer) = ring.split();
+    /// Audio chunks, taken from `pool`, are sent via chunk_tx.
+    pub fn start(
+        format: AudioFormat,
+        devices: DeviceSelection,
+        buffers: CaptureBuffers,
+        pool: BufferPool,
+        chunk_tx: mpsc::Sender<AudioChunk>,
+        events: CaptureEvents,
+    ) -> Result<CaptureStream> {
+        let chunk_size = format.samples_for_duration(buffers.chunk_seconds);
+        let (stream, input, notify) = Self::open(format, devices, buffers, events.device_lost)?;
 
+        tokio::task::spawn_local(Self::bridge_task(
+            input,
+            pool,
+            chunk_tx,
+            chunk_size,
+            notify,
+            LevelMeter::new(format, events.level),
+        ));
+
+        tracing::info!("Audio capture started");
+        Ok(stream)
+    }
+
+    /// Start audio capture that keeps the last `pre_roll_seconds` while idle
+    pub fn start_pre_roll(
+        format: AudioFormat,
+        devices: DeviceSelection,
+        buffers: CaptureBuffers,
+        pool: BufferPool,
+        pre_roll_seconds: f32,
+        events: CaptureEvents,
+    ) -> Result<PreRollCapture> {
+        let chunk_size = format.samples_for_duration(buffers.chunk_seconds);
+        let (stream, input, notify) = Self::open(format, devices, buffers, events.device_lost)?;
+        let (sessions, sessions_rx) = mpsc::unbounded_channel();
+
+        tokio::task::spawn_local(Self::pre_roll_bridge_task(
+            input,
+            pool,
+            sessions_rx,
+            chunk_size,
+            format.samples_for_duration(pre_roll_seconds),
+            notify,
+            LevelMeter::new(format, events.level),
+        ));
+
+        tracing::info!("Audio capture started with {}s pre-roll", pre_roll_seconds);
+        Ok(PreRollCapture { stream, sessions })
+    }
+
+    /// Open and start the input streams, each feeding its own ring buffer
+    ///
+    /// `notify` fires whenever a device has at least a chunk of samples waiting.
+    fn open(
+        format: AudioFormat,
+        devices: DeviceSelection,
+        buffers: CaptureBuffers,
+        device_lost: mpsc::UnboundedSender<String>,
+    ) -> Result<(CaptureStream, InputMixer, Arc<Notify>)> {
+        let chunk_size = format.samples_for_duration(buffers.chunk_seconds);
         let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .context("No input audio device available")?;
+        let (device, name) = match (devices.jack, devices.source) {
+            (Some(jack), _) => {
+                if devices.source != AudioSource::Microphone {
+                    tracing::warn!(
+                        "audio_source {:?} is not used with JACK; connect the ports instead",
+                        devices.source
+                    );
+                }
+                Self::jack_device(jack)?
+            }
+            (None, AudioSource::Monitor) => Self::select_monitor(&host, devices)?,
+            (None, _) => Self::select_device(&host, devices)?,
+        };
+
+        let mut inputs = vec![(device, name.clone())];
+        if devices.jack.is_none() && devices.source != AudioSource::Monitor {
+            inputs.extend(Self::extra_devices(&host, devices, &name)?);
+        }
+        if devices.jack.is_none() && devices.source == AudioSource::Both {
+            inputs.push(Self::select_monitor(&host, devices)?);
+        }
 
         let config = StreamConfig {
-            channels: format.channels,
+            channels: devices.channel.capture_channels(format),
             sample_rate: SampleRate(format.sample_rate),
             buffer_size: BufferSize::Default,
         };
-
         let notify = Arc::new(Notify::new());
-        let notify_callback = notify.clone();
 
+        let mut streams = Vec::with_capacity(inputs.len());
+        let mut consumers = Vec::with_capacity(inputs.len());
+        for (device, name) in inputs {
+            let ring = HeapRb::<f32>::new(format.samples_for_duration(buffers.ring_seconds));
+            let (producer, consumer) = ring.split();
+            let stream = Self::build_stream(
+                &device,
+                &config,
+                devices.channel,
+                producer,
+                chunk_size,
+                notify.clone(),
+                {
+                    let device_lost = device_lost.clone();
+                    move || {
+                        let _ = device_lost.send(name.clone());
+                    }
+                },
+            )?;
+            streams.push(stream);
+            consumers.push(consumer);
+        }
+
+        let input = InputMixer::new(
+            consumers,
+            devices.mix,
+            format.samples_for_duration(LOUDEST_BLOCK_SECONDS),
+            STALL_CHUNKS * chunk_size,
+        );
+        let stream = CaptureStream {
+            _streams: streams,
+            device: name,
+        };
+        Ok((stream, input, notify))
+    }
+
+    fn build_stream(
+        device: &cpal::Device,
+        config: &StreamConfig,
+        channel: InputChannel,
+        mut producer: HeapProd<f32>,
+        chunk_size: usize,
+        notify: Arc<Notify>,
+        on_device_lost: impl Fn() + Send + 'static,
+    ) -> Result<cpal::Stream> {
         let stream = device
             .build_input_stream(
-                &config,
+                config,
                 move |data: &[f32], _info: &cpal::InputCallbackInfo| {
-                    producer.push_slice(data);
-                    notify_callback.notify_one();
+                    // Selecting or mixing channels here keeps the rest of the
+                    // pipeline in the recording's format
+                    match channel {
+                        InputChannel::All => producer.push_slice(data),
+                        channel => producer
+                            .push_iter(data.chunks_exact(2).map(|frame| channel.downmix(frame))),
+                    };
+                    // Only wake the bridge once it has a full chunk to forward;
+                    // cpal callbacks are far more frequent than chunks.
+                    if producer.occupied_len() >= chunk_size {
+                        notify.notify_one();
+                    }
                 },
-                move |err| {
-                    eprintln!("Audio stream error: {}", err);
+                move |err| match err {
+                    // cpal stops delivering audio; the recorder switches devices
+                    cpal::StreamError::DeviceNotAvailable => on_device_lost(),
+                    err => tracing::warn!("Audio stream error: {}", err),
                 },
                 None,
             )
             .context("Failed to build input stream")?;
 
         stream.play().context("Failed to start audio stream")?;
+        Ok(stream)
+    }
 
-        let chunk_size = format.samples_for_duration(0.5);
-        tokio::task::spawn_local(Self::bridge_task(consumer, chunk_tx, chunk_size, notify));
+    /// Names of the connected input devices, for choosing `input_device`
+    pub fn list_input_devices() -> Result<Vec<String>> {
+        let host = cpal::default_host();
+        let devices = host
+            .input_devices()
+            .context("Failed to enumerate input devices")?;
+        Ok(devices.filter_map(|device| device.name().ok()).collect())
+    }
 
-        tracing::info!("Audio capture started");
-        Ok(stream)
+    /// Pick the configured device if it is connected, else the current default
+    ///
+    /// Called for every recording rather than once at startup, so a dock or
+    /// Bluetooth headset that appeared (or went away) while idle is picked up.
+    /// After a device failure, that device is skipped and any other input is
+    /// used if the default is the one that failed.
+    fn select_device(
+        host: &cpal::Host,
+        devices: DeviceSelection,
+    ) -> Result<(cpal::Device, String)> {
+        let usable = |name: &str| devices.failed != Some(name);
+
+        if let Some(preferred) = devices.preferred {
+            let needle = preferred.to_lowercase();
+            let found = host
+                .input_devices()
+                .context("Failed to enumerate input devices")?
+                .filter_map(|device| device.name().ok().map(|name| (device, name)))
+                .find(|(_, name)| usable(name) && name.to_lowercase().contains(&needle));
+
+            match found {
+                Some((device, name)) => {
+                    tracing::info!("Using input device {:?}", name);
+                    return Ok((device, name));
+                }
+                None => tracing::warn!(
+                    "Input device {:?} not found, using the default input",
+                    preferred
+                ),
+            }
+        }
+
+        if let Some(device) = host.default_input_device() {
+            let name = device.name().unwrap_or_default();
+            if usable(&name) {
+                tracing::info!("Using default input device {:?}", name);
+                return Ok((device, name));
+            }
+        }
+
+        // A monitor source would record the speakers instead of the microphone
+        let (device, name) = host
+            .input_devices()
+            .context("Failed to enumerate input devices")?
+            .filter_map(|device| device.name().ok().map(|name| (device, name)))
+            .find(|(_, name)| usable(name) && !is_monitor(name))
+            .context("No input audio device available")?;
+        tracing::info!("Falling back to input device {:?}", name);
+        Ok((device, name))
+    }
+
+    /// Find the monitor source to record the speakers from
+    ///
+    /// Unlike microphones there is no sensible fallback: recording the
+    /// microphone instead would silently miss the other side of a call.
+    fn select_monitor(
+        host: &cpal::Host,
+        devices: DeviceSelection,
+    ) -> Result<(cpal::Device, String)> {
+        let needle = devices.monitor.unwrap_or(MONITOR_NAME).to_lowercase();
+        let (device, name) = host
+            .input_devices()
+            .context("Failed to enumerate input devices")?
+            .filter_map(|device| device.name().ok().map(|name| (device, name)))
+            .find(|(_, name)| {
+                devices.failed != Some(name.as_str()) && name.to_lowercase().contains(&needle)
+            })
+            .with_context(|| {
+                format!(
+                    "No monitor source matching {:?}; see `dictator devices` and monitor_device",
+                    needle
+                )
+            })?;
+        tracing::info!("Recording monitor source {:?}", name);
+        Ok((device, name))
+    }
+
+    /// Find the `extra_input_devices`, skipping any that are not connected
+    ///
+    /// Names are matched like `input_device`. A device already in the
+    /// recording, or one that just failed, is not added twice.
+    fn extra_devices(
+        host: &cpal::Host,
+        devices: DeviceSelection,
+        main: &str,
+    ) -> Result<Vec<(cpal::Device, String)>> {
+        let mut found: Vec<(cpal::Device, String)> = Vec::new();
+        for wanted in devices.extra {
+            let needle = wanted.to_lowercase();
+            let device = host
+                .input_devices()
+                .context("Failed to enumerate input devices")?
+                .filter_map(|device| device.name().ok().map(|name| (device, name)))
+                .find(|(_, name)| {
+                    name != main
+                        && devices.failed != Some(name.as_str())
+                        && !found.iter().any(|(_, added)| added == name)
+                        && name.to_lowercase().contains(&needle)
+                });
+
+            match device {
+                Some((device, name)) => {
+                    tracing::info!("Also recording from input device {:?}", name);
+                    found.push((device, name));
+                }
+                None => tracing::warn!("Extra input device {:?} not found, skipping it", wanted),
+            }
+        }
+        Ok(found)
+    }
+
+    /// Register a JACK client whose input ports feed the recording
+    ///
+    /// JACK runs at the server's sample rate, so `sample_rate` must match it.
+    #[cfg(feature = "jack")]
+    fn jack_device(jack: &JackOptions) -> Result<(cpal::Device, String)> {
+        let mut host = cpal::platform::JackHost::new()
+            .map_err(|_| anyhow::anyhow!("JACK is not available"))?;
+        host.set_connect_automatically(jack.auto_connect);
+        let device = host
+            .input_device_with_name(&jack.client_name)
+            .context("Failed to create JACK client; is the JACK server running?")?;
+
+        tracing::info!("Capturing as JACK client {:?}", jack.client_name);
+        Ok((device.into(), jack.client_name.clone()))
+    }
+
+    #[cfg(not(feature = "jack"))]
+    fn jack_device(_jack: &JackOptions) -> Result<(cpal::Device, String)> {
+        anyhow::bail!("dictator was built without the jack feature")
     }
 
     async fn bridge_task(
-        mut consumer: impl Consumer<Item = f32>,
-        tx: mpsc::Sender<Vec<f32>>,
+        mut input: InputMixer,
+        pool: BufferPool,
+        tx: mpsc::Sender<AudioChunk>,
         chunk_size: usize,
         notify: Arc<Notify>,
+        meter: LevelMeter,
     ) {
+        let mut meter_tick = tokio::time::interval(LEVEL_INTERVAL);
         loop {
-            notify.notified().await;
+            tokio::select! {
+                _ = notify.notified() => {
+                    // Notifications coalesce, so forward every full chunk available
+                    while input.occupied_len() >= chunk_size {
+                        let chunk = Self::pop_chunk(&mut input, &pool, chunk_size);
+                        if tx.send(chunk).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                _ = meter_tick.tick() => {
+                    // The stream callback holds the other reference to `notify`,
+                    // so once it is gone the stream has been dropped
+                    if tx.is_closed() || Arc::strong_count(&notify) == 1 {
+                        return;
+                    }
+                    meter.publish(&input);
+                }
+            }
+        }
+    }
 
-            let available = consumer.occupied_len();
-            if available >= chunk_size {
-                let mut chunk = vec![0.0f32; chunk_size];
-                let n = consumer.pop_slice(&mut chunk);
-                chunk.truncate(n);
+    /// Like `bridge_task`, but between recordings the audio goes into a
+    /// history of the last `pre_roll` samples instead of a channel
+    async fn pre_roll_bridge_task(
+        mut input: InputMixer,
+        pool: BufferPool,
+        mut sessions: mpsc::UnboundedReceiver<mpsc::Sender<AudioChunk>>,
+        chunk_size: usize,
+        pre_roll: usize,
+        notify: Arc<Notify>,
+        meter: LevelMeter,
+    ) {
+        let mut history = VecDeque::with_capacity(pre_roll + chunk_size);
+        let mut target: Option<mpsc::Sender<AudioChunk>> = None;
+        let mut meter_tick = tokio::time::interval(LEVEL_INTERVAL);
+
+        loop {
+            tokio::select! {
+                session = sessions.recv() => {
+                    let Some(tx) = session else { return };
+                    // Audio still in the ring buffer is forwarded with the next
+                    // chunk, so the history only makes up the difference
+                    let keep = pre_roll
+                        .saturating_sub(input.occupied_len())
+                        .min(history.len());
+                    let mut chunk = pool.take();
+                    chunk
+                        .as_mut_vec()
+                        .extend(history.range(history.len() - keep..));
+                    history.clear();
 
-                if tx.send(chunk).await.is_err() {
-                    break;
+                    if !chunk.is_empty() && tx.send(chunk).await.is_err() {
+                        continue;
+                    }
+                    target = Some(tx);
+                }
+                _ = notify.notified() => {
+                    while input.occupied_len() >= chunk_size {
+                        let chunk = Self::pop_chunk(&mut input, &pool, chunk_size);
+                        // The recorder drops its receiver once the recording ends
+                        match target.take().filter(|tx| !tx.is_closed()) {
+                            Some(tx) => {
+                                if tx.send(chunk).await.is_ok() {
+                                    target = Some(tx);
+                                }
+                            }
+                            None => {
+                                history.extend(chunk.iter());
+                                let excess = history.len().saturating_sub(pre_roll);
+                                history.drain(..excess);
+                            }
+                        }
+                    }
+                }
+                _ = meter_tick.tick() => {
+                    if target.as_ref().is_some_and(|tx| !tx.is_closed()) {
+                        meter.publish(&input);
+                    }
                 }
             }
         }
     }
+
+    fn pop_chunk(input: &mut InputMixer, pool: &BufferPool, chunk_size: usize) -> AudioChunk {
+        let mut chunk = pool.take();
+        let buffer = chunk.as_mut_vec();
+        buffer.resize(chunk_size, 0.0);
+        let n = input.pop_slice(buffer);
+        buffer.truncate(n);
+        chunk
+    }
+}
+
+/// Publishes the level of the most recent audio in the ring buffers
+struct LevelMeter {
+    level: watch::Sender<InputLevel>,
+    /// Samples measured per update
+    block: usize,
+}
+
+impl LevelMeter {
+    fn new(format: AudioFormat, level: watch::Sender<InputLevel>) -> Self {
+        let block = format.samples_for_duration(LEVEL_INTERVAL.as_secs_f32());
+        Self { level, block }
+    }
+
+    fn publish(&self, input: &InputMixer) {
+        if let Some(level) = input.recent_level(self.block) {
+            self.level.send_replace(level);
+        }
+    }
 }
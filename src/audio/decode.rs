@@ -0,0 +1,69 @@
+use super::RecordedAudio;
+use anyhow::{Context, Result};
+use std::io::Cursor;
+
+/// Decode a WAV recording into mono samples for a recognizer fed raw audio
+pub fn decode_wav(audio: &RecordedAudio, sample_rate: u32) -> Result<Vec<f32>> {
+    match audio {
+        RecordedAudio::File(file) => read_samples(
+            hound::WavReader::open(file.path()).context("Failed to open recording")?,
+            sample_rate,
+        ),
+        RecordedAudio::Memory(bytes) => read_samples(
+            hound::WavReader::new(Cursor::new(bytes.as_slice()))
+                .context("Failed to read recording")?,
+            sample_rate,
+        ),
+    }
+}
+
+fn read_samples<R: std::io::Read>(
+    reader: hound::WavReader<R>,
+    sample_rate: u32,
+) -> Result<Vec<f32>> {
+    let spec = reader.spec();
+    if spec.sample_rate != sample_rate {
+        anyhow::bail!(
+            "The recognizer needs {} Hz audio, the recording is {} Hz",
+            sample_rate,
+            spec.sample_rate
+        );
+    }
+
+    let samples = reader
+        .into_samples::<i16>()
+        .map(|sample| sample.map(|s| s as f32 / i16::MAX as f32))
+        .collect::<Result<Vec<f32>, _>>()
+        .context("Failed to decode recording")?;
+
+    // Stereo recordings are averaged, as recognizers only take one channel
+    let channels = spec.channels.max(1) as usize;
+    Ok(samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_stereo_wav() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut bytes = Vec::new();
+        let mut writer = hound::WavWriter::new(Cursor::new(&mut bytes), spec).unwrap();
+        for sample in [i16::MAX, 0, 0, 0] {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let samples = decode_wav(&RecordedAudio::Memory(bytes), 16000).unwrap();
+        assert_eq!(samples, [0.5, 0.0]);
+    }
+}
@@ -9,6 +9,11 @@ pub enum FeedbackSoundType {
     Start,
     Stop,
     Complete,
+    Warning,
+    /// A recording too short to transcribe was thrown away
+    Discarded,
+    /// Recording started while the transcription service is unreachable
+    Unavailable,
 }
 
 pub struct AudioFeedback {
@@ -16,13 +21,28 @@ pub struct AudioFeedback {
 }
 
 impl AudioFeedback {
+    /// Create feedback player for already-resolved sound file paths
     pub fn new(paths: HashMap<FeedbackSoundType, PathBuf>) -> Self {
         Self { paths }
     }
 
     pub async fn play(&self, sound_type: FeedbackSoundType) {
-        if let Some(path) = self.paths.get(&sound_type) {
-            play_sound(path.clone()).await;
+        match (sound_type, self.paths.get(&sound_type)) {
+            (_, Some(path)) => play_sound(path.clone()).await,
+            // Without a sound of its own, a double stop sound tells a discarded
+            // recording apart from one that is being transcribed
+            (FeedbackSoundType::Discarded, None) => {
+                if let Some(path) = self.paths.get(&FeedbackSoundType::Stop) {
+                    play_sound(path.clone()).await;
+                    play_sound(path.clone()).await;
+                }
+            }
+            (FeedbackSoundType::Unavailable, None) => {
+                if let Some(path) = self.paths.get(&FeedbackSoundType::Warning) {
+                    play_sound(path.clone()).await;
+                }
+            }
+            (_, None) => {}
         }
     }
 }
@@ -38,9 +58,7 @@ async fn play_sound(path: PathBuf) {
 }
 
 fn play_sound_blocking(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let file = File::open(path)
-        .or_else(|_| File::open(PathBuf::from("assets").join(path)))
-        .or_else(|_| File::open(PathBuf::from("/usr/share/dictator/assets").join(path)))?;
+    let file = File::open(path)?;
 
     let stream_handle = OutputStreamBuilder::open_default_stream()?;
     let sink = rodio::play(stream_handle.mixer(), BufReader::new(file))?;
@@ -0,0 +1,368 @@
+use super::buffer_pool::AudioChunk;
+use super::format::AudioFormat;
+use super::sink::AudioSink;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use tokio::sync::{mpsc, oneshot};
+
+/// Samples per channel in each FLAC frame
+const BLOCK_SIZE: usize = 4096;
+
+/// Highest fixed predictor order tried; higher orders rarely help for speech
+const MAX_FIXED_ORDER: usize = 2;
+
+/// Largest Rice parameter expressible with the 4-bit parameter field (15 is an escape code)
+const MAX_RICE_PARAMETER: u32 = 14;
+
+/// Byte offset of the STREAMINFO field holding the total sample count, which
+/// is only known once the recording ends
+const STREAM_INFO_FIELD_OFFSET: u64 = 18;
+
+enum FlacCommand {
+    WriteChunk(AudioChunk),
+    Finalize { reply: oneshot::Sender<Result<()>> },
+}
+
+/// Lossless FLAC encoder using a dedicated blocking thread, like `WavSink`
+///
+/// Each channel is coded with the best of the fixed predictors and a single
+/// Rice partition, which gets most of FLAC's gain on speech with no extra
+/// dependencies.
+pub struct FlacSink {
+    tx: mpsc::UnboundedSender<FlacCommand>,
+}
+
+impl FlacSink {
+    pub fn new(path: PathBuf, format: AudioFormat) -> Result<Self> {
+        let mut writer = FlacWriter::create(path, format)?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        std::thread::spawn(move || {
+            while let Some(cmd) = rx.blocking_recv() {
+                match cmd {
+                    FlacCommand::WriteChunk(samples) => {
+                        if let Err(e) = writer.write(&samples) {
+                            eprintln!("Failed to encode samples: {}", e);
+                            break;
+                        }
+                    }
+                    FlacCommand::Finalize { reply } => {
+                        let _ = reply.send(writer.finish());
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self { tx })
+    }
+}
+
+#[async_trait]
+impl AudioSink for FlacSink {
+    fn write_chunk(&mut self, samples: AudioChunk) -> Result<()> {
+        self.tx
+            .send(FlacCommand::WriteChunk(samples))
+            .map_err(|e| anyhow::anyhow!("Failed to send write command: {}", e))
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        let (reply, rx) = oneshot::channel();
+        self.tx
+            .send(FlacCommand::Finalize { reply })
+            .map_err(|e| anyhow::anyhow!("Failed to send finalize command: {}", e))?;
+
+        rx.await
+            .map_err(|e| anyhow::anyhow!("Failed to receive finalize response: {}", e))?
+    }
+}
+
+/// Buffers samples into blocks and writes each block as a FLAC frame
+struct FlacWriter {
+    file: BufWriter<File>,
+    format: AudioFormat,
+    /// Interleaved samples waiting for a full block
+    pending: Vec<i16>,
+    frame_number: u64,
+    /// Samples per channel written so far
+    total_samples: u64,
+}
+
+impl FlacWriter {
+    fn create(path: PathBuf, format: AudioFormat) -> Result<Self> {
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create FLAC file: {:?}", path))?;
+        let mut file = BufWriter::new(file);
+
+        file.write_all(b"fLaC")?;
+        // Last metadata block, type STREAMINFO, 34 bytes long
+        file.write_all(&[0x80, 0, 0, 34])?;
+        let block_size = (BLOCK_SIZE as u16).to_be_bytes();
+        file.write_all(&block_size)?; // minimum block size
+        file.write_all(&block_size)?; // maximum block size
+        file.write_all(&[0; 6])?; // frame sizes: unknown
+        file.write_all(&stream_info_field(format, 0).to_be_bytes())?;
+        file.write_all(&[0; 16])?; // MD5 of the audio: not computed
+
+        Ok(Self {
+            file,
+            format,
+            pending: Vec::new(),
+            frame_number: 0,
+            total_samples: 0,
+        })
+    }
+
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        // Convert f32 (-1.0 to 1.0) to i16, as for WAV
+        self.pending.extend(
+            samples
+                .iter()
+                .map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16),
+        );
+
+        let block_len = BLOCK_SIZE * self.format.channels as usize;
+        while self.pending.len() >= block_len {
+            let block: Vec<i16> = self.pending.drain(..block_len).collect();
+            self.write_frame(&block)?;
+        }
+        Ok(())
+    }
+
+    /// Write the final short block and fill in the total sample count
+    fn finish(mut self) -> Result<()> {
+        if !self.pending.is_empty() {
+            let block = std::mem::take(&mut self.pending);
+            self.write_frame(&block)?;
+        }
+
+        let mut file = self
+            .file
+            .into_inner()
+            .map_err(|e| anyhow::anyhow!("Failed to flush FLAC file: {}", e))?;
+        file.seek(SeekFrom::Start(STREAM_INFO_FIELD_OFFSET))?;
+        file.write_all(&stream_info_field(self.format, self.total_samples).to_be_bytes())?;
+        file.sync_data().context("Failed to finalize FLAC file")
+    }
+
+    fn write_frame(&mut self, samples: &[i16]) -> Result<()> {
+        let channels = self.format.channels as usize;
+        let block_size = samples.len() / channels;
+        let mut out = BitWriter::default();
+
+        out.write(0b11_1111_1111_1110, 14); // sync code
+        out.write(0, 1); // reserved
+        out.write(0, 1); // fixed block size; frames are numbered
+        out.write(0b0111, 4); // block size: 16 bits at the end of the header
+        out.write(0b0000, 4); // sample rate: from STREAMINFO
+        out.write(channels as u64 - 1, 4); // independent channels
+        out.write(0b100, 3); // 16 bits per sample
+        out.write(0, 1); // reserved
+        for byte in utf8_number(self.frame_number) {
+            out.write(byte as u64, 8);
+        }
+        out.write(block_size as u64 - 1, 16);
+        let header_crc = crc8(&out.bytes);
+        out.write(header_crc as u64, 8);
+
+        for channel in 0..channels {
+            let channel_samples: Vec<i64> = samples
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .map(|&sample| sample as i64)
+                .collect();
+            write_fixed_subframe(&mut out, &channel_samples);
+        }
+
+        out.align();
+        let frame_crc = crc16(&out.bytes);
+        out.write(frame_crc as u64, 16);
+
+        self.file
+            .write_all(&out.bytes)
+            .context("Failed to write FLAC frame")?;
+        self.frame_number += 1;
+        self.total_samples += block_size as u64;
+        Ok(())
+    }
+}
+
+/// Sample rate, channels, bits per sample and total samples, as packed in STREAMINFO
+fn stream_info_field(format: AudioFormat, total_samples: u64) -> u64 {
+    (format.sample_rate as u64) << 44
+        | (format.channels as u64 - 1) << 41
+        | (AudioFormat::BITS_PER_SAMPLE as u64 - 1) << 36
+        | total_samples & ((1 << 36) - 1)
+}
+
+/// Code one channel with the fixed predictor that leaves the smallest residual
+fn write_fixed_subframe(out: &mut BitWriter, samples: &[i64]) {
+    let (order, residuals) = (0..=MAX_FIXED_ORDER.min(samples.len()))
+        .map(|order| (order, fixed_residuals(samples, order)))
+        .min_by_key(|(_, residuals)| residuals.iter().map(|r| r.unsigned_abs()).sum::<u64>())
+        .expect("order 0 is always available");
+    let parameter = rice_parameter(&residuals);
+
+    out.write(0, 1); // padding
+    out.write(0b001000 | order as u64, 6); // SUBFRAME_FIXED
+    out.write(0, 1); // no wasted bits
+    for &sample in &samples[..order] {
+        out.write(sample as i16 as u16 as u64, 16);
+    }
+
+    out.write(0, 2); // Rice coding with 4-bit parameters
+    out.write(0, 4); // a single partition
+    out.write(parameter as u64, 4);
+    for &residual in &residuals {
+        let value = zigzag(residual);
+        out.write_unary(value >> parameter);
+        out.write(value, parameter);
+    }
+}
+
+/// Prediction residuals of FLAC's fixed polynomial predictor of the given order
+fn fixed_residuals(samples: &[i64], order: usize) -> Vec<i64> {
+    (order..samples.len())
+        .map(|i| match order {
+            0 => samples[i],
+            1 => samples[i] - samples[i - 1],
+            _ => samples[i] - 2 * samples[i - 1] + samples[i - 2],
+        })
+        .collect()
+}
+
+/// Rice parameter giving the fewest bits for these residuals
+fn rice_parameter(residuals: &[i64]) -> u32 {
+    (0..=MAX_RICE_PARAMETER)
+        .min_by_key(|&parameter| {
+            residuals
+                .iter()
+                .map(|&residual| (zigzag(residual) >> parameter) + 1 + parameter as u64)
+                .sum::<u64>()
+        })
+        .unwrap_or(0)
+}
+
+/// Map signed residuals to unsigned values: 0, -1, 1, -2, ... -> 0, 1, 2, 3, ...
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Frame number in the extended UTF-8 coding used by FLAC frame headers
+fn utf8_number(value: u64) -> Vec<u8> {
+    if value < 0x80 {
+        return vec![value as u8];
+    }
+
+    let mut continuation = Vec::new();
+    let mut rest = value;
+    // Each continuation byte holds 6 bits; the lead byte holds what is left
+    while rest >= 1 << (6 - continuation.len()) {
+        continuation.push(0x80 | (rest & 0x3f) as u8);
+        rest >>= 6;
+    }
+    let count = continuation.len() + 1;
+    let lead = !(0xffu8 >> count) | rest as u8;
+
+    let mut bytes = vec![lead];
+    bytes.extend(continuation.iter().rev());
+    bytes
+}
+
+/// CRC-8 of a frame header (polynomial x^8 + x^2 + x + 1)
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// CRC-16 of a whole frame (polynomial x^16 + x^15 + x^2 + 1)
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |crc, &byte| {
+        (0..8).fold(crc ^ (byte as u16) << 8, |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// MSB-first bit packer for one frame
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    /// Append the low `bits` bits of `value` (at most 32)
+    fn write(&mut self, value: u64, bits: u32) {
+        self.acc = (self.acc << bits) | (value & ((1 << bits) - 1));
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.acc >> self.bits) as u8);
+        }
+        self.acc &= (1 << self.bits) - 1;
+    }
+
+    /// Append `value` zeros followed by a one
+    fn write_unary(&mut self, mut value: u64) {
+        while value >= 32 {
+            self.write(0, 32);
+            value -= 32;
+        }
+        self.write(1, value as u32 + 1);
+    }
+
+    /// Pad with zeros to the next byte boundary
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flac_round_trip() {
+        let format = AudioFormat::default();
+        let samples: Vec<f32> = (0..10_000)
+            .map(|i| (i as f32 * 0.05).sin() * 0.5 + if i % 7 == 0 { 0.01 } else { 0.0 })
+            .collect();
+
+        let file = tempfile::Builder::new().suffix(".flac").tempfile().unwrap();
+        let mut writer = FlacWriter::create(file.path().to_path_buf(), format).unwrap();
+        for chunk in samples.chunks(1600) {
+            writer.write(chunk).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut reader = claxon::FlacReader::open(file.path()).unwrap();
+        assert_eq!(reader.streaminfo().samples, Some(samples.len() as u64));
+        let decoded: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+        let expected: Vec<i32> = samples
+            .iter()
+            .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16 as i32)
+            .collect();
+        assert_eq!(decoded, expected);
+        assert!(std::fs::metadata(file.path()).unwrap().len() < samples.len() as u64 * 2);
+    }
+}
@@ -2,6 +2,8 @@
 // All audio processing, capture, and encoding is done with this format.
 // If we need to support other formats in the future, this will need to be parameterized.
 
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Clone, Copy)]
 pub struct AudioFormat {
     pub sample_rate: u32,
@@ -11,12 +13,21 @@ pub struct AudioFormat {
 impl AudioFormat {
     pub const BITS_PER_SAMPLE: u16 = 16;
 
-    /// Calculate number of samples for a given duration in seconds
+    /// Calculate number of interleaved samples, across all channels, for a
+    /// given duration in seconds
     pub fn samples_for_duration(&self, seconds: f32) -> usize {
-        (self.sample_rate as f32 * seconds) as usize
+        (self.sample_rate as f32 * seconds) as usize * self.channels as usize
     }
 }
 
+/// Samples as 16-bit little-endian PCM, for streaming them to a server
+pub fn pcm_bytes(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|sample| ((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+        .collect()
+}
+
 impl Default for AudioFormat {
     fn default() -> Self {
         Self {
@@ -25,3 +36,77 @@ impl Default for AudioFormat {
         }
     }
 }
+
+/// Which channel of a stereo input to record (`input_channel`)
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputChannel {
+    /// Record the device's channels as they are
+    #[default]
+    All,
+    Left,
+    Right,
+    /// Average both channels into one
+    Mix,
+}
+
+impl InputChannel {
+    /// Channels to open the input device with
+    pub fn capture_channels(self, format: AudioFormat) -> u16 {
+        match self {
+            Self::All => format.channels,
+            Self::Left | Self::Right | Self::Mix => 2,
+        }
+    }
+
+    /// The recorded sample for one captured stereo frame
+    pub fn downmix(self, frame: &[f32]) -> f32 {
+        match self {
+            Self::All | Self::Left => frame[0],
+            Self::Right => frame[1],
+            Self::Mix => (frame[0] + frame[1]) / 2.0,
+        }
+    }
+}
+
+/// Container and codec recordings are encoded to before upload
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadFormat {
+    #[default]
+    Wav,
+    /// Lossless and about half the size of WAV
+    Flac,
+    /// Ogg/Opus, about a tenth of the size; needs the `opus` cargo feature
+    Opus,
+}
+
+impl UploadFormat {
+    /// File extension, which tells the transcription server the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Wav => ".wav",
+            Self::Flac => ".flac",
+            Self::Opus => ".ogg",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_channel_downmix() {
+        let frame = [0.5, -0.25];
+        assert_eq!(InputChannel::Left.downmix(&frame), 0.5);
+        assert_eq!(InputChannel::Right.downmix(&frame), -0.25);
+        assert_eq!(InputChannel::Mix.downmix(&frame), 0.125);
+
+        let format = AudioFormat::default();
+        assert_eq!(InputChannel::All.capture_channels(format), 1);
+        assert_eq!(InputChannel::Mix.capture_channels(format), 2);
+
+        assert_eq!(pcm_bytes(&[0.0, 1.0, -2.0]), [0, 0, 0xff, 0x7f, 0x01, 0x80]);
+    }
+}
@@ -0,0 +1,196 @@
+use super::format::AudioFormat;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Quietest level meters show; anything below reads as silence
+const FLOOR_DBFS: f32 = -60.0;
+
+/// Samples at or above this magnitude count as clipped
+const CLIP_LEVEL: f32 = 0.99;
+
+/// Share of clipped samples above which the recording is likely distorted
+const MAX_CLIPPED_FRACTION: f64 = 0.001;
+
+/// Recordings whose loudest sample stays below this (about -34 dBFS) are
+/// too quiet for reliable transcription
+const MIN_PEAK: f32 = 0.02;
+
+/// Loudness of the most recent input, for level meters
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputLevel {
+    /// Root mean square of the samples, 0.0 to 1.0
+    pub rms: f32,
+    /// Largest absolute sample, 0.0 to 1.0
+    pub peak: f32,
+}
+
+impl InputLevel {
+    pub fn measure<'a>(samples: impl IntoIterator<Item = &'a f32>) -> Self {
+        let (mut sum, mut count, mut peak) = (0.0f32, 0usize, 0.0f32);
+        for sample in samples {
+            sum += sample * sample;
+            count += 1;
+            peak = peak.max(sample.abs());
+        }
+        if count == 0 {
+            return Self::default();
+        }
+        Self {
+            rms: (sum / count as f32).sqrt(),
+            peak,
+        }
+    }
+
+    pub fn rms_dbfs(&self) -> f32 {
+        to_dbfs(self.rms)
+    }
+
+    pub fn peak_dbfs(&self) -> f32 {
+        to_dbfs(self.peak)
+    }
+
+    /// A bar of `width` characters: `#` up to the RMS level, `|` at the peak
+    pub fn meter(&self, width: usize) -> String {
+        let position = |dbfs: f32| {
+            let fraction = (dbfs - FLOOR_DBFS) / -FLOOR_DBFS;
+            (fraction * width as f32).round() as usize
+        };
+        let rms = position(self.rms_dbfs());
+        let peak = position(self.peak_dbfs()).min(width);
+
+        (0..width)
+            .map(|i| match i {
+                i if i < rms => '#',
+                i if i + 1 == peak => '|',
+                _ => '-',
+            })
+            .collect()
+    }
+}
+
+/// Level statistics over a whole recording, for spotting microphone problems
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RecordingStats {
+    samples: u64,
+    clipped: u64,
+    peak: f32,
+    sum_squares: f64,
+}
+
+impl RecordingStats {
+    pub fn add(&mut self, samples: &[f32]) {
+        for sample in samples {
+            let magnitude = sample.abs();
+            self.peak = self.peak.max(magnitude);
+            self.sum_squares += f64::from(sample * sample);
+            if magnitude >= CLIP_LEVEL {
+                self.clipped += 1;
+            }
+        }
+        self.samples += samples.len() as u64;
+    }
+
+    /// Length of the recorded audio, leaving out paused stretches
+    pub fn duration(&self, format: AudioFormat) -> Duration {
+        let frames = self.samples / format.channels.max(1) as u64;
+        Duration::from_secs_f64(frames as f64 / format.sample_rate as f64)
+    }
+
+    /// Average level of the whole recording
+    pub fn rms_dbfs(&self) -> f32 {
+        if self.samples == 0 {
+            return FLOOR_DBFS;
+        }
+        to_dbfs((self.sum_squares / self.samples as f64).sqrt() as f32)
+    }
+
+    /// A warning if the input level probably hurts the transcription
+    pub fn warning(&self) -> Option<&'static str> {
+        if self.samples == 0 {
+            return None;
+        }
+        if self.clipped as f64 / self.samples as f64 > MAX_CLIPPED_FRACTION {
+            Some("Microphone level too high, the recording is clipping; transcription may be poor")
+        } else if self.peak < MIN_PEAK {
+            Some("Microphone level too low; transcription may be poor")
+        } else {
+            None
+        }
+    }
+}
+
+fn to_dbfs(amplitude: f32) -> f32 {
+    (20.0 * amplitude.log10()).max(FLOOR_DBFS)
+}
+
+/// Draw a level meter on the terminal while recording (`--verbose`)
+pub fn spawn_terminal_meter(mut levels: watch::Receiver<InputLevel>) {
+    if !std::io::stderr().is_terminal() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        while levels.changed().await.is_ok() {
+            let level = *levels.borrow_and_update();
+            let mut stderr = std::io::stderr().lock();
+            // The level is reset once a recording ends, which clears the line
+            let _ = if level == InputLevel::default() {
+                write!(stderr, "\r\x1b[K")
+            } else {
+                write!(
+                    stderr,
+                    "\r[{}] {:>5.1} dBFS",
+                    level.meter(40),
+                    level.rms_dbfs()
+                )
+            };
+            let _ = stderr.flush();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_meter() {
+        let level = InputLevel::measure(&[0.1, -0.1, 0.1, -1.0]);
+        assert_eq!(level.peak, 1.0);
+        assert!((level.rms_dbfs() - -5.9).abs() < 0.1);
+        assert_eq!(level.meter(10), "#########|");
+
+        assert_eq!(InputLevel::measure(&[]).meter(4), "----");
+        assert_eq!(InputLevel::default().rms_dbfs(), FLOOR_DBFS);
+    }
+
+    #[test]
+    fn test_recording_stats_warnings() {
+        let speech: Vec<f32> = (0..16_000).map(|i| (i as f32 * 0.05).sin() * 0.3).collect();
+        let mut stats = RecordingStats::default();
+        stats.add(&speech);
+        assert_eq!(stats.warning(), None);
+
+        let mut quiet = RecordingStats::default();
+        quiet.add(&speech.iter().map(|s| s * 0.01).collect::<Vec<_>>());
+        assert!(quiet.warning().unwrap().contains("too low"));
+        assert!((stats.rms_dbfs() - -13.5).abs() < 0.1);
+        assert!(quiet.rms_dbfs() < stats.rms_dbfs() - 39.0);
+
+        let mut clipping = RecordingStats::default();
+        clipping.add(
+            &speech
+                .iter()
+                .map(|s| (s * 10.0).clamp(-1.0, 1.0))
+                .collect::<Vec<_>>(),
+        );
+        assert!(clipping.warning().unwrap().contains("clipping"));
+
+        assert_eq!(RecordingStats::default().warning(), None);
+        assert_eq!(
+            stats.duration(AudioFormat::default()),
+            Duration::from_secs_f64(stats.samples as f64 / 16000.0)
+        );
+    }
+}
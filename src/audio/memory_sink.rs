@@ -0,0 +1,98 @@
+use super::buffer_pool::AudioChunk;
+use super::format::AudioFormat;
+use super::sink::AudioSink;
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// Size of the RIFF/WAVE header written before the samples
+const WAV_HEADER_LEN: usize = 44;
+
+/// WAV encoder that keeps the whole file in memory
+///
+/// Used with `audio_in_memory` so recordings are uploaded straight from RAM
+/// and never touch the disk. Writing to a `Vec` is cheap enough to do inline,
+/// so unlike `WavSink` there is no I/O thread.
+pub struct MemorySink {
+    format: AudioFormat,
+    bytes: Vec<u8>,
+}
+
+impl MemorySink {
+    pub fn new(format: AudioFormat) -> Self {
+        Self {
+            format,
+            // Sizes are filled in by `finalize`
+            bytes: vec![0; WAV_HEADER_LEN],
+        }
+    }
+
+    /// The encoded WAV file
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[async_trait]
+impl AudioSink for MemorySink {
+    fn write_chunk(&mut self, samples: AudioChunk) -> Result<()> {
+        self.bytes.reserve(samples.len() * 2);
+        for &sample in samples.iter() {
+            // Convert f32 (-1.0 to 1.0) to i16
+            let amplitude = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.bytes.extend_from_slice(&amplitude.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        let data_len = u32::try_from(self.bytes.len() - WAV_HEADER_LEN)
+            .map_err(|_| anyhow::anyhow!("Recording is too long for a WAV file"))?;
+        let header = wav_header(self.format, data_len);
+        self.bytes[..WAV_HEADER_LEN].copy_from_slice(&header);
+        Ok(())
+    }
+}
+
+/// Canonical 44-byte header for 16-bit PCM
+fn wav_header(format: AudioFormat, data_len: u32) -> Vec<u8> {
+    let block_align = format.channels * AudioFormat::BITS_PER_SAMPLE / 8;
+    let byte_rate = format.sample_rate * block_align as u32;
+
+    let mut header = Vec::with_capacity(WAV_HEADER_LEN);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_len).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    header.extend_from_slice(&format.channels.to_le_bytes());
+    header.extend_from_slice(&format.sample_rate.to_le_bytes());
+    header.extend_from_slice(&byte_rate.to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&AudioFormat::BITS_PER_SAMPLE.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::BufferPool;
+
+    #[tokio::test]
+    async fn test_memory_sink_writes_valid_wav() {
+        let format = AudioFormat::default();
+        let pool = BufferPool::new(4, 1);
+        let mut chunk = pool.take();
+        chunk.as_mut_vec().extend_from_slice(&[0.0, 0.5, -0.5, 1.0]);
+
+        let mut sink = MemorySink::new(format);
+        sink.write_chunk(chunk).unwrap();
+        sink.finalize().await.unwrap();
+
+        let reader = hound::WavReader::new(std::io::Cursor::new(sink.into_bytes())).unwrap();
+        assert_eq!(reader.spec().sample_rate, 16000);
+        let samples: Vec<i16> = reader.into_samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples, vec![0, 16383, -16383, i16::MAX]);
+    }
+}
@@ -0,0 +1,160 @@
+use super::level::InputLevel;
+use ringbuf::{HeapCons, traits::*};
+use serde::{Deserialize, Serialize};
+
+/// How the inputs of a multi-device recording are combined (`input_mix`)
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputMix {
+    /// Add the inputs together
+    #[default]
+    Mix,
+    /// Take whichever input is loudest, block by block, so only the
+    /// microphone being spoken into is heard
+    Loudest,
+}
+
+/// Reads the ring buffers of every device in a recording as one stream
+///
+/// Devices run on their own clocks, so one may deliver audio slightly ahead
+/// of another; reads wait for the slowest. A device that stops delivering
+/// altogether would hold up the rest, so once it falls `stall_len` samples
+/// behind its part is read as silence.
+pub struct InputMixer {
+    consumers: Vec<HeapCons<f32>>,
+    mix: InputMix,
+    /// Samples each `Loudest` choice covers
+    block_len: usize,
+    stall_len: usize,
+    scratch: Vec<Vec<f32>>,
+}
+
+impl InputMixer {
+    pub fn new(
+        consumers: Vec<HeapCons<f32>>,
+        mix: InputMix,
+        block_len: usize,
+        stall_len: usize,
+    ) -> Self {
+        let scratch = vec![Vec::new(); consumers.len()];
+        Self {
+            consumers,
+            mix,
+            block_len: block_len.max(1),
+            stall_len,
+            scratch,
+        }
+    }
+
+    /// Samples that can be read
+    pub fn occupied_len(&self) -> usize {
+        let lens = self
+            .consumers
+            .iter()
+            .map(|consumer| consumer.occupied_len());
+        let most = lens.clone().max().unwrap_or(0);
+        let least = lens.min().unwrap_or(0);
+        if most - least > self.stall_len {
+            most
+        } else {
+            least
+        }
+    }
+
+    /// Read up to `out.len()` mixed samples, returning how many were read
+    pub fn pop_slice(&mut self, out: &mut [f32]) -> usize {
+        if let [consumer] = self.consumers.as_mut_slice() {
+            return consumer.pop_slice(out);
+        }
+
+        let n = out.len().min(self.occupied_len());
+        for (consumer, scratch) in self.consumers.iter_mut().zip(&mut self.scratch) {
+            scratch.clear();
+            scratch.resize(n, 0.0);
+            consumer.pop_slice(scratch);
+        }
+
+        match self.mix {
+            InputMix::Mix => {
+                for (i, sample) in out[..n].iter_mut().enumerate() {
+                    let sum: f32 = self.scratch.iter().map(|input| input[i]).sum();
+                    *sample = sum.clamp(-1.0, 1.0);
+                }
+            }
+            InputMix::Loudest => {
+                for start in (0..n).step_by(self.block_len) {
+                    let end = (start + self.block_len).min(n);
+                    let loudest = self
+                        .scratch
+                        .iter()
+                        .max_by(|a, b| energy(&a[start..end]).total_cmp(&energy(&b[start..end])))
+                        .expect("a mixer has at least one input");
+                    out[start..end].copy_from_slice(&loudest[start..end]);
+                }
+            }
+        }
+        n
+    }
+
+    /// Level of the last `len` samples waiting in the loudest input
+    pub fn recent_level(&self, len: usize) -> Option<InputLevel> {
+        self.consumers
+            .iter()
+            .filter_map(|consumer| {
+                let (head, tail) = consumer.as_slices();
+                let available = head.len() + tail.len();
+                (available > 0).then(|| {
+                    InputLevel::measure(head.iter().chain(tail).skip(available.saturating_sub(len)))
+                })
+            })
+            .max_by(|a, b| a.rms.total_cmp(&b.rms))
+    }
+}
+
+fn energy(samples: &[f32]) -> f32 {
+    samples.iter().map(|s| s * s).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringbuf::HeapRb;
+
+    fn mixer(inputs: &[&[f32]], mix: InputMix) -> InputMixer {
+        let consumers = inputs
+            .iter()
+            .map(|samples| {
+                let (mut producer, consumer) = HeapRb::<f32>::new(64).split();
+                producer.push_slice(samples);
+                consumer
+            })
+            .collect();
+        InputMixer::new(consumers, mix, 2, 4)
+    }
+
+    #[test]
+    fn test_mixer_combines_inputs() {
+        let headset: &[f32] = &[0.5, 0.5, 0.0, 0.0];
+        let desk: &[f32] = &[0.1, -0.1, 0.2, -0.2];
+        let mut out = [0.0; 4];
+
+        assert_eq!(
+            mixer(&[headset, desk], InputMix::Mix).pop_slice(&mut out),
+            4
+        );
+        assert_eq!(out, [0.6, 0.4, 0.2, -0.2]);
+
+        mixer(&[headset, desk], InputMix::Loudest).pop_slice(&mut out);
+        assert_eq!(out, [0.5, 0.5, 0.2, -0.2]);
+
+        // Reads wait for the slowest input until it has clearly stalled
+        let short: &[f32] = &[0.1; 2];
+        assert_eq!(mixer(&[headset, short], InputMix::Mix).occupied_len(), 2);
+        let long: &[f32] = &[0.1; 8];
+        let mut stalled = mixer(&[long, short], InputMix::Mix);
+        assert_eq!(stalled.occupied_len(), 8);
+        let mut out = [0.0; 8];
+        assert_eq!(stalled.pop_slice(&mut out), 8);
+        assert_eq!(out[7], 0.1);
+    }
+}
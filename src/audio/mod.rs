@@ -1,13 +1,34 @@
+pub mod buffer_pool;
 pub mod capture;
+#[cfg(any(feature = "local", feature = "vosk", feature = "websocket"))]
+pub mod decode;
 pub mod feedback;
+pub mod flac_sink;
 pub mod format;
+pub mod gain;
+pub mod level;
+pub mod memory_sink;
+pub mod mixer;
+#[cfg(feature = "opus")]
+pub mod opus_sink;
 pub mod recorder;
 pub mod sink;
 pub mod wav_sink;
 
-pub use capture::AudioCapture;
+pub use buffer_pool::{AudioChunk, BufferPool};
+pub use capture::{
+    AudioCapture, AudioHost, AudioSource, CaptureBuffers, CaptureEvents, CaptureStream,
+    DeviceSelection, JackOptions, PreRollCapture,
+};
 pub use feedback::AudioFeedback;
-pub use format::AudioFormat;
-pub use recorder::Recorder;
+pub use flac_sink::FlacSink;
+pub use format::{AudioFormat, InputChannel, UploadFormat, pcm_bytes};
+pub use gain::AutoGain;
+pub use level::{InputLevel, RecordingStats};
+pub use memory_sink::MemorySink;
+pub use mixer::InputMix;
+#[cfg(feature = "opus")]
+pub use opus_sink::OpusSink;
+pub use recorder::{AudioTap, RecordedAudio, Recorder, RecorderOptions};
 pub use sink::AudioSink;
 pub use wav_sink::WavSink;
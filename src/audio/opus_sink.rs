@@ -0,0 +1,250 @@
+use super::buffer_pool::AudioChunk;
+use super::format::AudioFormat;
+use super::sink::AudioSink;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use audiopus::coder::Encoder;
+use audiopus::{Application, Bitrate, Channels, SampleRate};
+use ogg::{PacketWriteEndInfo, PacketWriter};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use tokio::sync::{mpsc, oneshot};
+
+/// Length of each Opus frame; 20 ms is the usual choice for speech
+const FRAME_MS: u32 = 20;
+
+/// Target bitrate, plenty for speech recognition at a fraction of WAV's size
+const BITRATE: i32 = 24_000;
+
+/// Ogg Opus granule positions always count samples at 48 kHz
+const GRANULE_RATE: u32 = 48_000;
+
+/// Largest packet libopus recommends allowing for
+const MAX_PACKET_SIZE: usize = 4000;
+
+/// The file holds a single logical stream
+const STREAM_SERIAL: u32 = 1;
+
+enum OpusCommand {
+    WriteChunk(AudioChunk),
+    Finalize { reply: oneshot::Sender<Result<()>> },
+}
+
+/// Ogg/Opus encoder using a dedicated blocking thread, like `WavSink`
+///
+/// Uploads are roughly a tenth of the size of the equivalent 16-bit WAV,
+/// which matters over slow links to a remote Whisper server.
+pub struct OpusSink {
+    tx: mpsc::UnboundedSender<OpusCommand>,
+}
+
+impl OpusSink {
+    pub fn new(path: PathBuf, format: AudioFormat) -> Result<Self> {
+        let mut writer = OggOpusWriter::create(path, format)?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        std::thread::spawn(move || {
+            while let Some(cmd) = rx.blocking_recv() {
+                match cmd {
+                    OpusCommand::WriteChunk(samples) => {
+                        if let Err(e) = writer.write(&samples) {
+                            eprintln!("Failed to encode samples: {}", e);
+                            break;
+                        }
+                    }
+                    OpusCommand::Finalize { reply } => {
+                        let _ = reply.send(writer.finish());
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self { tx })
+    }
+}
+
+#[async_trait]
+impl AudioSink for OpusSink {
+    fn write_chunk(&mut self, samples: AudioChunk) -> Result<()> {
+        self.tx
+            .send(OpusCommand::WriteChunk(samples))
+            .map_err(|e| anyhow::anyhow!("Failed to send write command: {}", e))
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        let (reply, rx) = oneshot::channel();
+        self.tx
+            .send(OpusCommand::Finalize { reply })
+            .map_err(|e| anyhow::anyhow!("Failed to send finalize command: {}", e))?;
+
+        rx.await
+            .map_err(|e| anyhow::anyhow!("Failed to receive finalize response: {}", e))?
+    }
+}
+
+/// Encodes samples into fixed-size Opus frames and writes them as Ogg pages
+struct OggOpusWriter {
+    encoder: Encoder,
+    writer: PacketWriter<BufWriter<File>>,
+    /// Samples waiting for a full frame
+    pending: Vec<f32>,
+    /// Samples per frame across all channels
+    frame_len: usize,
+    channels: usize,
+    /// Encoder delay in input samples per channel
+    lookahead: u64,
+    /// Samples per channel received from the recording
+    samples_in: u64,
+    /// Samples per channel fed to the encoder, including padding
+    samples_encoded: u64,
+    /// 48 kHz samples per input sample
+    granule_scale: u64,
+}
+
+impl OggOpusWriter {
+    fn create(path: PathBuf, format: AudioFormat) -> Result<Self> {
+        let sample_rate = SampleRate::try_from(format.sample_rate as i32)
+            .context("Opus does not support the recording sample rate")?;
+        let channels = Channels::try_from(format.channels as i32)
+            .context("Opus does not support the recording channel count")?;
+
+        let mut encoder = Encoder::new(sample_rate, channels, Application::Voip)
+            .context("Failed to create Opus encoder")?;
+        encoder
+            .set_bitrate(Bitrate::BitsPerSecond(BITRATE))
+            .context("Failed to set Opus bitrate")?;
+        let lookahead = encoder
+            .lookahead()
+            .context("Failed to query Opus encoder delay")? as u64;
+
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create Opus file: {:?}", path))?;
+        let mut writer = PacketWriter::new(BufWriter::new(file));
+
+        let granule_scale = (GRANULE_RATE / format.sample_rate) as u64;
+        let pre_skip = (lookahead * granule_scale) as u16;
+        // The identification and comment headers each need a page of their own
+        for header in [
+            opus_head(format.channels as u8, pre_skip, format.sample_rate),
+            opus_tags(),
+        ] {
+            writer
+                .write_packet(
+                    header.into_boxed_slice(),
+                    STREAM_SERIAL,
+                    PacketWriteEndInfo::EndPage,
+                    0,
+                )
+                .context("Failed to write Ogg Opus header")?;
+        }
+
+        let channels = format.channels as usize;
+        Ok(Self {
+            encoder,
+            writer,
+            pending: Vec::new(),
+            frame_len: (format.sample_rate * FRAME_MS / 1000) as usize * channels,
+            channels,
+            lookahead,
+            samples_in: 0,
+            samples_encoded: 0,
+            granule_scale,
+        })
+    }
+
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        self.samples_in += (samples.len() / self.channels) as u64;
+        self.pending.extend_from_slice(samples);
+
+        while self.pending.len() >= self.frame_len {
+            let frame: Vec<f32> = self.pending.drain(..self.frame_len).collect();
+            self.encode_frame(&frame, PacketWriteEndInfo::NormalPacket, None)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the remaining samples and end the stream
+    ///
+    /// Silence is appended so the encoder's delay is flushed too, and the last
+    /// granule position trims the padding off again on decode.
+    fn finish(mut self) -> Result<()> {
+        let padding = self.lookahead as usize * self.channels;
+        self.pending.extend(std::iter::repeat_n(0.0, padding));
+        let frames = self.pending.len().div_ceil(self.frame_len).max(1);
+        self.pending.resize(frames * self.frame_len, 0.0);
+
+        let end_granule = (self.lookahead + self.samples_in) * self.granule_scale;
+        let pending = std::mem::take(&mut self.pending);
+        let mut frames = pending.chunks(self.frame_len).peekable();
+        while let Some(frame) = frames.next() {
+            if frames.peek().is_some() {
+                self.encode_frame(frame, PacketWriteEndInfo::NormalPacket, None)?;
+            } else {
+                self.encode_frame(frame, PacketWriteEndInfo::EndStream, Some(end_granule))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_frame(
+        &mut self,
+        frame: &[f32],
+        end: PacketWriteEndInfo,
+        granule: Option<u64>,
+    ) -> Result<()> {
+        let mut packet = vec![0u8; MAX_PACKET_SIZE];
+        let len = self
+            .encoder
+            .encode_float(frame, &mut packet)
+            .context("Failed to encode Opus frame")?;
+        packet.truncate(len);
+
+        self.samples_encoded += (frame.len() / self.channels) as u64;
+        let granule = granule.unwrap_or(self.samples_encoded * self.granule_scale);
+        self.writer
+            .write_packet(packet.into_boxed_slice(), STREAM_SERIAL, end, granule)
+            .context("Failed to write Opus packet")
+    }
+}
+
+/// Ogg Opus identification header (RFC 7845, section 5.1)
+fn opus_head(channels: u8, pre_skip: u16, input_sample_rate: u32) -> Vec<u8> {
+    let mut head = b"OpusHead".to_vec();
+    head.push(1); // version
+    head.push(channels);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&input_sample_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // channel mapping family: mono/stereo
+    head
+}
+
+/// Ogg Opus comment header with no user comments (RFC 7845, section 5.2)
+fn opus_tags() -> Vec<u8> {
+    let vendor = b"dictator";
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes());
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opus_head() {
+        let head = opus_head(1, 312, 16000);
+        assert_eq!(head.len(), 19);
+        assert_eq!(&head[..8], b"OpusHead");
+        assert_eq!(u16::from_le_bytes([head[10], head[11]]), 312);
+        assert_eq!(
+            u32::from_le_bytes([head[12], head[13], head[14], head[15]]),
+            16000
+        );
+    }
+}
@@ -1,105 +1,512 @@
-use crate::audio::{AudioCapture, AudioFormat, AudioSink, WavSink};
-use anyhow::Result;
+use crate::audio::{
+    AudioCapture, AudioChunk, AudioFormat, AudioSink, AudioSource, AutoGain, BufferPool,
+    CaptureBuffers, CaptureEvents, CaptureStream, DeviceSelection, FlacSink, InputChannel,
+    InputLevel, InputMix, JackOptions, MemorySink, PreRollCapture, RecordingStats, UploadFormat,
+    WavSink,
+};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use tempfile::NamedTempFile;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, watch};
 use tokio::task::JoinHandle;
 
+/// Prefix of the temporary files recordings are written to
+const TEMP_FILE_PREFIX: &str = "dictator-";
+
+/// Temporary recordings older than this were left behind by a dictator that
+/// was killed; no recording in progress is anywhere near this long
+const STALE_TEMP_FILE_AGE: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Receives a copy of every chunk written to the recording, for backends that
+/// transcribe while recording; closed when the recording ends
+pub type AudioTap = mpsc::UnboundedSender<Vec<f32>>;
+
+/// Controls a running recording task
+enum RecorderCommand {
+    /// Drop captured audio until resumed; the stream keeps running
+    Pause,
+    Resume,
+    /// Flush and finalize the audio file for transcription
+    Stop,
+    /// Discard the recording without finalizing it
+    Cancel,
+}
+
+/// Audio of a finished recording, ready for upload
+pub enum RecordedAudio {
+    /// Encoded to a temporary file, deleted when dropped
+    File(NamedTempFile),
+    /// WAV held in memory (`audio_in_memory`), never written to disk
+    Memory(Vec<u8>),
+}
+
+impl std::fmt::Debug for RecordedAudio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(file) => write!(f, "{:?}", file.path()),
+            Self::Memory(bytes) => write!(f, "{} bytes in memory", bytes.len()),
+        }
+    }
+}
+
+/// How recordings are captured and stored
+#[derive(Debug, Clone, Default)]
+pub struct RecorderOptions {
+    pub upload_format: UploadFormat,
+    /// Encode into memory instead of a temporary file
+    pub in_memory: bool,
+    /// Preferred input device name (substring), else the default input
+    pub input_device: Option<String>,
+    /// Further devices recorded alongside the main one
+    pub extra_input_devices: Vec<String>,
+    /// How the devices are combined
+    pub input_mix: InputMix,
+    /// Record the microphone, the monitor or both
+    pub audio_source: AudioSource,
+    /// Monitor source name (substring), else any input named "monitor"
+    pub monitor_device: Option<String>,
+    /// Channel of a stereo input to record
+    pub input_channel: InputChannel,
+    /// Capture as a JACK client instead of from `input_device`
+    pub jack: Option<JackOptions>,
+    /// Target level for automatic gain control; `None` leaves levels untouched
+    pub auto_gain_target_dbfs: Option<f32>,
+    /// Audio from before the recording starts to include; 0 disables pre-roll
+    pub pre_roll_ms: u64,
+    /// Chunk and ring buffer sizes
+    pub buffers: CaptureBuffers,
+    /// Directory for temporary recordings; `None` uses the system default
+    pub temp_dir: Option<PathBuf>,
+}
+
 /// Manages audio recording lifecycle
 ///
 /// Spawns recording tasks on-demand when start() is called.
 /// Holds the cpal::Stream (which is !Send) but spawns Send tasks for actual recording work.
+///
+/// The audio host and input device are resolved in `start()` and the stream is
+/// dropped in `stop()` or `cancel()`, so no ALSA/PipeWire resources are held while idle.
+/// The exception is pre-roll, which keeps one stream open from `open_pre_roll()` on.
 pub struct Recorder {
     format: AudioFormat,
-    stream: Option<cpal::Stream>,
-    task_handle: Option<JoinHandle<Result<NamedTempFile>>>,
-    stop_tx: Option<oneshot::Sender<()>>,
+    options: RecorderOptions,
+    pool: BufferPool,
+    stream: Option<CaptureStream>,
+    pre_roll: Option<PreRollCapture>,
+    /// Sender for the current recording's chunks, to reattach after a device switch
+    audio_tx: Option<mpsc::Sender<AudioChunk>>,
+    events: CaptureEvents,
+    device_lost_rx: mpsc::UnboundedReceiver<String>,
+    task_handle: Option<JoinHandle<Result<(RecordedAudio, RecordingStats)>>>,
+    command_tx: Option<mpsc::UnboundedSender<RecorderCommand>>,
 }
 
 impl Recorder {
-    pub fn new(format: AudioFormat) -> Self {
+    pub fn new(format: AudioFormat, options: RecorderOptions) -> Self {
+        // A few chunks are in flight between capture, recorder and sink at once
+        let pool = BufferPool::new(
+            format.samples_for_duration(options.buffers.chunk_seconds),
+            8,
+        );
+        let (device_lost, device_lost_rx) = mpsc::unbounded_channel();
+        let events = CaptureEvents {
+            device_lost,
+            level: watch::Sender::new(InputLevel::default()),
+        };
+
         Self {
             format,
+            options,
+            pool,
             stream: None,
+            pre_roll: None,
+            audio_tx: None,
+            events,
+            device_lost_rx,
             task_handle: None,
-            stop_tx: None,
+            command_tx: None,
+        }
+    }
+
+    /// Keep the microphone open from now on if `pre_roll_ms` is set, so
+    /// recordings can include the audio from just before they start
+    pub fn open_pre_roll(&mut self) -> Result<()> {
+        if self.options.pre_roll_ms == 0 {
+            return Ok(());
+        }
+
+        self.pre_roll = Some(self.start_pre_roll(None)?);
+        Ok(())
+    }
+
+    fn start_pre_roll(&self, failed: Option<&str>) -> Result<PreRollCapture> {
+        AudioCapture::start_pre_roll(
+            self.format,
+            self.devices(failed),
+            self.options.buffers,
+            self.pool.clone(),
+            self.options.pre_roll_ms as f32 / 1000.0,
+            self.events.clone(),
+        )
+    }
+
+    fn devices<'a>(&'a self, failed: Option<&'a str>) -> DeviceSelection<'a> {
+        DeviceSelection {
+            preferred: self.options.input_device.as_deref(),
+            failed,
+            channel: self.options.input_channel,
+            jack: self.options.jack.as_ref(),
+            extra: &self.options.extra_input_devices,
+            mix: self.options.input_mix,
+            source: self.options.audio_source,
+            monitor: self.options.monitor_device.as_deref(),
         }
     }
 
-    pub fn start(&mut self) -> Result<()> {
-        if self.stream.is_some() {
+    /// Record from `source` from the next recording on, e.g. after a profile switch
+    ///
+    /// An open pre-roll stream is reopened on the new source.
+    pub fn set_audio_source(&mut self, source: AudioSource) -> Result<()> {
+        if source == self.options.audio_source {
+            return Ok(());
+        }
+
+        tracing::info!("Audio source is now {:?}", source);
+        self.options.audio_source = source;
+        if self.pre_roll.take().is_some() {
+            self.pre_roll = Some(self.start_pre_roll(None)?);
+        }
+        Ok(())
+    }
+
+    /// Start recording, also sending the audio to `tap` as it is captured
+    pub fn start(&mut self, tap: Option<AudioTap>) -> Result<()> {
+        if self.task_handle.is_some() {
             return Err(anyhow::anyhow!("Recording already in progress"));
         }
 
         let (audio_tx, audio_rx) = mpsc::channel(100);
-        let stream = AudioCapture::start(self.format, audio_tx)?;
+        self.capture_into(audio_tx.clone(), None)?;
+        self.audio_tx = Some(audio_tx);
 
-        let (stop_tx, stop_rx) = oneshot::channel();
-        let task_handle = tokio::spawn(recording_task(self.format, audio_rx, stop_rx));
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let task_handle = tokio::spawn(recording_task(
+            self.format,
+            self.options.clone(),
+            tap,
+            audio_rx,
+            command_rx,
+        ));
 
-        self.stream = Some(stream);
         self.task_handle = Some(task_handle);
-        self.stop_tx = Some(stop_tx);
+        self.command_tx = Some(command_tx);
 
         tracing::info!("Recording started");
         Ok(())
     }
 
-    pub async fn stop(&mut self) -> Result<NamedTempFile> {
-        let stream = self
-            .stream
-            .take()
-            .ok_or_else(|| anyhow::anyhow!("No recording in progress"))?;
+    /// Send captured audio to `audio_tx`, from the pre-roll stream if open
+    fn capture_into(
+        &mut self,
+        audio_tx: mpsc::Sender<AudioChunk>,
+        failed: Option<&str>,
+    ) -> Result<()> {
+        match &self.pre_roll {
+            Some(pre_roll) => pre_roll.record(audio_tx),
+            None => {
+                self.stream = Some(AudioCapture::start(
+                    self.format,
+                    self.devices(failed),
+                    self.options.buffers,
+                    self.pool.clone(),
+                    audio_tx,
+                    self.events.clone(),
+                )?);
+                Ok(())
+            }
+        }
+    }
+
+    /// Live input level, updated while recording and reset when it ends
+    pub fn levels(&self) -> watch::Receiver<InputLevel> {
+        self.events.level.subscribe()
+    }
+
+    /// Name of the device currently being captured from, if any
+    fn current_device(&self) -> Option<&str> {
+        match (&self.pre_roll, &self.stream) {
+            (Some(pre_roll), _) => Some(pre_roll.device()),
+            (None, Some(stream)) => Some(&stream.device),
+            (None, None) => None,
+        }
+    }
+
+    /// Wait until the device being captured from disappears, returning its name
+    pub async fn device_lost(&mut self) -> String {
+        while let Some(device) = self.device_lost_rx.recv().await {
+            // Streams that have since been replaced may still report
+            if self.current_device() == Some(device.as_str()) {
+                return device;
+            }
+        }
+        std::future::pending().await
+    }
+
+    /// Carry on capturing from another device after `failed` disappeared
+    ///
+    /// A recording in progress continues in the same file, so only the audio
+    /// from the moment of the failure is lost.
+    pub fn switch_device(&mut self, failed: &str) -> Result<()> {
+        self.stream = None;
+        if self.pre_roll.take().is_some() {
+            self.pre_roll = Some(self.start_pre_roll(Some(failed))?);
+        }
+        if let Some(audio_tx) = self.audio_tx.clone() {
+            self.capture_into(audio_tx, Some(failed))?;
+        }
+        Ok(())
+    }
+
+    /// Stop writing audio to the recording until `resume()`
+    pub fn pause(&self) -> Result<()> {
+        self.send(RecorderCommand::Pause)?;
+        tracing::info!("Recording paused");
+        Ok(())
+    }
+
+    pub fn resume(&self) -> Result<()> {
+        self.send(RecorderCommand::Resume)?;
+        tracing::info!("Recording resumed");
+        Ok(())
+    }
 
-        let stop_tx = self
-            .stop_tx
+    fn send(&self, command: RecorderCommand) -> Result<()> {
+        self.command_tx
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No recording in progress"))?
+            .send(command)
+            .map_err(|_| anyhow::anyhow!("Recording task has stopped"))
+    }
+
+    /// Stop and finalize the recording, returning it with its level statistics
+    pub async fn stop(&mut self) -> Result<(RecordedAudio, RecordingStats)> {
+        let recording = self.finish(RecorderCommand::Stop).await?;
+        tracing::info!("Recording stopped");
+        Ok(recording)
+    }
+
+    /// Stop capturing and discard the recording, deleting any temp file
+    pub async fn cancel(&mut self) -> Result<()> {
+        drop(self.finish(RecorderCommand::Cancel).await?);
+        tracing::info!("Recording cancelled");
+        Ok(())
+    }
+
+    async fn finish(
+        &mut self,
+        command: RecorderCommand,
+    ) -> Result<(RecordedAudio, RecordingStats)> {
+        let command_tx = self
+            .command_tx
             .take()
-            .ok_or_else(|| anyhow::anyhow!("No stop signal sender"))?;
+            .ok_or_else(|| anyhow::anyhow!("No recording in progress"))?;
 
         let task_handle = self
             .task_handle
             .take()
             .ok_or_else(|| anyhow::anyhow!("No task handle"))?;
 
-        drop(stream);
-        let _ = stop_tx.send(());
+        // A pre-roll stream stays open; it stops forwarding once the
+        // recording task drops its receiver
+        drop(self.stream.take());
+        self.audio_tx = None;
+        self.events.level.send_replace(InputLevel::default());
+        let _ = command_tx.send(command);
 
-        let result = task_handle
+        task_handle
             .await
-            .map_err(|e| anyhow::anyhow!("Recording task panicked: {}", e))??;
-
-        tracing::info!("Recording stopped");
-        Ok(result)
+            .map_err(|e| anyhow::anyhow!("Recording task panicked: {}", e))?
     }
 }
 
 async fn recording_task(
     format: AudioFormat,
-    mut audio_rx: mpsc::Receiver<Vec<f32>>,
-    mut stop_rx: oneshot::Receiver<()>,
-) -> Result<NamedTempFile> {
-    let temp_file = tempfile::Builder::new()
-        .prefix("dictator-")
-        .suffix(".wav")
-        .tempfile()?;
+    options: RecorderOptions,
+    tap: Option<AudioTap>,
+    audio_rx: mpsc::Receiver<AudioChunk>,
+    command_rx: mpsc::UnboundedReceiver<RecorderCommand>,
+) -> Result<(RecordedAudio, RecordingStats)> {
+    let gain = options
+        .auto_gain_target_dbfs
+        .map(|target| AutoGain::new(format, target));
+
+    if options.in_memory {
+        let mut sink = MemorySink::new(format);
+        let stats = record_into(&mut sink, gain, tap, audio_rx, command_rx).await?;
+        return Ok((RecordedAudio::Memory(sink.into_bytes()), stats));
+    }
+
+    let mut builder = tempfile::Builder::new();
+    builder
+        .prefix(TEMP_FILE_PREFIX)
+        .suffix(options.upload_format.extension());
+    let temp_file = match &options.temp_dir {
+        Some(dir) => builder.tempfile_in(dir),
+        None => builder.tempfile(),
+    }
+    .context("Failed to create temporary recording file")?;
 
     let path = temp_file.path().to_path_buf();
-    let mut sink = WavSink::new(path, format)?;
+    let mut sink = create_sink(options.upload_format, path, format)?;
+    let stats = record_into(sink.as_mut(), gain, tap, audio_rx, command_rx).await?;
+    Ok((RecordedAudio::File(temp_file), stats))
+}
 
+/// Feed captured chunks to the sink until told to stop, then finalize it
+///
+/// Chunks arriving while paused are dropped, so the paused stretch is left
+/// out of the recording. On cancel the sink is left unfinalized; the caller
+/// discards the recording.
+async fn record_into(
+    sink: &mut dyn AudioSink,
+    mut gain: Option<AutoGain>,
+    tap: Option<AudioTap>,
+    mut audio_rx: mpsc::Receiver<AudioChunk>,
+    mut command_rx: mpsc::UnboundedReceiver<RecorderCommand>,
+) -> Result<RecordingStats> {
+    let mut stats = RecordingStats::default();
+    let mut paused = false;
     loop {
         tokio::select! {
             Some(chunk) = audio_rx.recv() => {
-                sink.write_chunk(chunk)?;
+                if !paused {
+                    write_chunk(sink, &mut gain, &mut stats, tap.as_ref(), chunk)?;
+                }
             }
-            _ = &mut stop_rx => {
-                break;
+            command = command_rx.recv() => match command {
+                Some(RecorderCommand::Pause) => paused = true,
+                Some(RecorderCommand::Resume) => paused = false,
+                // Dropping the sink closes the file without flushing pending chunks
+                Some(RecorderCommand::Cancel) => return Ok(stats),
+                Some(RecorderCommand::Stop) | None => break,
             }
         }
     }
 
     while let Ok(chunk) = audio_rx.try_recv() {
-        sink.write_chunk(chunk)?;
+        if !paused {
+            write_chunk(sink, &mut gain, &mut stats, tap.as_ref(), chunk)?;
+        }
     }
 
     sink.finalize().await?;
-    Ok(temp_file)
+    Ok(stats)
+}
+
+fn write_chunk(
+    sink: &mut dyn AudioSink,
+    gain: &mut Option<AutoGain>,
+    stats: &mut RecordingStats,
+    tap: Option<&AudioTap>,
+    mut chunk: AudioChunk,
+) -> Result<()> {
+    if let Some(gain) = gain {
+        gain.process(chunk.as_mut_vec());
+    }
+    stats.add(&chunk);
+    if let Some(tap) = tap {
+        // A backend that gave up on the stream still gets the recording file
+        let _ = tap.send(chunk.to_vec());
+    }
+    sink.write_chunk(chunk)
+}
+
+fn create_sink(
+    upload_format: UploadFormat,
+    path: std::path::PathBuf,
+    format: AudioFormat,
+) -> Result<Box<dyn AudioSink>> {
+    match upload_format {
+        UploadFormat::Wav => Ok(Box::new(WavSink::new(path, format)?)),
+        UploadFormat::Flac => Ok(Box::new(FlacSink::new(path, format)?)),
+        #[cfg(feature = "opus")]
+        UploadFormat::Opus => Ok(Box::new(crate::audio::OpusSink::new(path, format)?)),
+        #[cfg(not(feature = "opus"))]
+        UploadFormat::Opus => anyhow::bail!("dictator was built without the opus feature"),
+    }
+}
+
+/// Copy a recording kept elsewhere into a temporary recording file, to hand it
+/// to the pipeline like a new recording without the original being deleted
+pub fn copy_to_temp_file(path: &Path, temp_dir: Option<&Path>) -> Result<RecordedAudio> {
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let mut builder = tempfile::Builder::new();
+    let suffix = format!(".{}", extension);
+    builder.prefix(TEMP_FILE_PREFIX).suffix(&suffix);
+    let temp_file = match temp_dir {
+        Some(dir) => builder.tempfile_in(dir),
+        None => builder.tempfile(),
+    }
+    .context("Failed to create temporary recording file")?;
+    std::fs::copy(path, temp_file.path())
+        .with_context(|| format!("Failed to copy recording {:?}", path))?;
+    Ok(RecordedAudio::File(temp_file))
+}
+
+/// Delete temporary recordings in `dir` left behind by a dictator that was
+/// killed before it could clean up, returning how many were removed
+pub fn sweep_stale_temp_files(dir: &Path) -> Result<usize> {
+    let now = SystemTime::now();
+    let extensions = [UploadFormat::Wav, UploadFormat::Flac, UploadFormat::Opus]
+        .map(|format| format.extension());
+
+    let mut removed = 0;
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read directory: {:?}", dir))?;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !name.starts_with(TEMP_FILE_PREFIX)
+            || !extensions.iter().any(|extension| name.ends_with(extension))
+        {
+            continue;
+        }
+
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| {
+                now.duration_since(modified).unwrap_or_default() > STALE_TEMP_FILE_AGE
+            });
+        if stale && entry.file_type().is_ok_and(|kind| kind.is_file()) {
+            let path = entry.path();
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to delete stale recording {:?}", path))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sweep_stale_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = SystemTime::now() - 2 * STALE_TEMP_FILE_AGE;
+        for name in ["dictator-old.wav", "dictator-old.txt", "other-old.wav"] {
+            let file = std::fs::File::create(dir.path().join(name)).unwrap();
+            file.set_modified(old).unwrap();
+        }
+        std::fs::write(dir.path().join("dictator-recording.flac"), b"").unwrap();
+
+        assert_eq!(sweep_stale_temp_files(dir.path()).unwrap(), 1);
+        assert!(!dir.path().join("dictator-old.wav").exists());
+        assert!(dir.path().join("dictator-old.txt").exists());
+        assert!(dir.path().join("other-old.wav").exists());
+        assert!(dir.path().join("dictator-recording.flac").exists());
+    }
 }
@@ -1,3 +1,4 @@
+use super::buffer_pool::AudioChunk;
 use anyhow::Result;
 use async_trait::async_trait;
 
@@ -8,8 +9,8 @@ use async_trait::async_trait;
 #[async_trait]
 pub trait AudioSink: Send {
     /// Write audio samples (streaming, called repeatedly during recording)
-    /// The Vec is moved to avoid copying
-    fn write_chunk(&mut self, samples: Vec<f32>) -> Result<()>;
+    /// The chunk is moved to avoid copying; dropping it returns the buffer to its pool
+    fn write_chunk(&mut self, samples: AudioChunk) -> Result<()>;
 
     /// Finalize and close the sink
     async fn finalize(&mut self) -> Result<()>;
@@ -1,3 +1,4 @@
+use super::buffer_pool::AudioChunk;
 use super::format::AudioFormat;
 use super::sink::AudioSink;
 use anyhow::Result;
@@ -7,7 +8,7 @@ use std::path::PathBuf;
 use tokio::sync::{mpsc, oneshot};
 
 enum WavCommand {
-    WriteChunk(Vec<f32>),
+    WriteChunk(AudioChunk),
     Finalize { reply: oneshot::Sender<Result<()>> },
 }
 
@@ -38,7 +39,7 @@ impl WavSink {
             while let Some(cmd) = rx.blocking_recv() {
                 match cmd {
                     WavCommand::WriteChunk(samples) => {
-                        for sample in samples {
+                        for &sample in samples.iter() {
                             // Convert f32 (-1.0 to 1.0) to i16
                             let amplitude = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
                             if let Err(e) = writer.write_sample(amplitude) {
@@ -65,7 +66,7 @@ impl WavSink {
 
 #[async_trait]
 impl AudioSink for WavSink {
-    fn write_chunk(&mut self, samples: Vec<f32>) -> Result<()> {
+    fn write_chunk(&mut self, samples: AudioChunk) -> Result<()> {
         self.tx
             .send(WavCommand::WriteChunk(samples))
             .map_err(|e| anyhow::anyhow!("Failed to send write command: {}", e))
@@ -0,0 +1,193 @@
+use crate::app;
+use crate::audio::recorder;
+use crate::config::Config;
+use crate::text_processing::TextProcessor;
+use crate::transcript_format::TranscriptFormat;
+use crate::transcription::{Transcriber, Transcript, TranscriptionConfig};
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use std::path::{Path, PathBuf};
+
+/// Extensions of the audio files `dictator batch` transcribes
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "wav", "flac", "ogg", "oga", "opus", "mp3", "m4a", "mp4", "mpeg", "mpga", "webm",
+];
+
+/// How the files of a batch went
+#[derive(Debug, Default, PartialEq)]
+pub struct BatchSummary {
+    pub transcribed: usize,
+    /// Files that already had a transcript
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// Transcribes existing audio files like dictations, minus the injection
+struct FileTranscriber {
+    transcriber: Transcriber,
+    config: TranscriptionConfig,
+    text_processor: TextProcessor,
+    temp_dir: Option<PathBuf>,
+}
+
+impl FileTranscriber {
+    /// Set up the configured backend with the active profile's settings
+    async fn new(config: &Config) -> Result<Self> {
+        let settings = config.profile_settings(config.active_profile.as_deref());
+        Ok(Self {
+            transcriber: Transcriber::new(config).await?,
+            config: app::transcription_config(config, &settings, ""),
+            text_processor: app::build_text_processor(&settings),
+            temp_dir: config.temp_dir.clone(),
+        })
+    }
+
+    /// The transcript of `file`, its text and segments processed
+    async fn transcribe(&self, file: &Path) -> Result<Transcript> {
+        // Handed over as a copy, so the original isn't deleted afterwards
+        let audio = recorder::copy_to_temp_file(file, self.temp_dir.as_deref())?;
+        let mut transcript = self.transcriber.transcribe(&audio, &self.config).await?;
+        transcript.text = self.text_processor.process(&transcript.text);
+        for segment in &mut transcript.segments {
+            segment.text = self.text_processor.process(&segment.text);
+        }
+        Ok(transcript)
+    }
+}
+
+/// Transcribe one audio file, processed with word overrides and punctuation
+/// commands as a dictation would be (`dictator transcribe`)
+pub async fn transcribe_file(
+    config: &Config,
+    file: &Path,
+    format: TranscriptFormat,
+) -> Result<String> {
+    if !file.is_file() {
+        anyhow::bail!("No such file: {:?}", file);
+    }
+    let transcript = FileTranscriber::new(config).await?.transcribe(file).await?;
+    format.render(&transcript)
+}
+
+/// Transcribe every audio file under `dir` with the configured backend, `jobs`
+/// at a time, writing each transcript in `format` next to the file
+///
+/// The active profile's settings apply, as for a dictation. Files that
+/// already have a transcript are skipped unless `force` is set, so an
+/// interrupted batch can be run again.
+pub async fn run(
+    config: &Config,
+    dir: &Path,
+    jobs: usize,
+    force: bool,
+    format: TranscriptFormat,
+) -> Result<BatchSummary> {
+    let mut files = audio_files(dir)?;
+    files.sort();
+
+    let mut summary = BatchSummary::default();
+    if !force {
+        files.retain(|file| {
+            let done = transcript_path(file, format).exists();
+            if done {
+                summary.skipped += 1;
+            }
+            !done
+        });
+    }
+    if files.is_empty() {
+        return Ok(summary);
+    }
+
+    let transcriber = FileTranscriber::new(config).await?;
+    let mut results = futures::stream::iter(files)
+        .map(|file| {
+            let transcriber = &transcriber;
+            async move {
+                let result = transcriber.transcribe(&file).await;
+                (file, result)
+            }
+        })
+        .buffer_unordered(jobs.max(1));
+
+    while let Some((file, result)) = results.next().await {
+        let written = result.and_then(|transcript| {
+            let path = transcript_path(&file, format);
+            std::fs::write(&path, format.render(&transcript)?)
+                .with_context(|| format!("Failed to write transcript {:?}", path))?;
+            Ok(path)
+        });
+        match written {
+            Ok(path) => {
+                println!("{} -> {}", file.display(), path.display());
+                summary.transcribed += 1;
+            }
+            Err(e) => {
+                eprintln!("{}: {:#}", file.display(), e);
+                summary.failed += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Audio files under `dir`, in subdirectories too
+fn audio_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read directory: {:?}", dir))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(kind) = entry.file_type() else {
+            continue;
+        };
+        if kind.is_dir() {
+            files.extend(audio_files(&path)?);
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Where the transcript of `file` goes: `memo.m4a` becomes `memo.txt`, or
+/// `memo.srt` for subtitles
+fn transcript_path(file: &Path, format: TranscriptFormat) -> PathBuf {
+    file.with_extension(format.extension())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("march")).unwrap();
+        for name in ["memo.m4a", "notes.txt", "march/call.MP3", "march/cover.jpg"] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+
+        let mut files = audio_files(dir.path()).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                dir.path().join("march/call.MP3"),
+                dir.path().join("memo.m4a")
+            ]
+        );
+        assert_eq!(
+            transcript_path(&files[1], TranscriptFormat::Text),
+            dir.path().join("memo.txt")
+        );
+        assert_eq!(
+            transcript_path(&files[1], TranscriptFormat::Srt),
+            dir.path().join("memo.srt")
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use crate::tokenizer::{self, Rewriter, says};
+
+/// Marks words said with "no caps", so sentence capitalization leaves them
+/// lowercase; `TextProcessor` removes it afterwards
+pub const KEEP_CASE: char = '\u{E000}';
+
+/// An identifier style that can be dictated, e.g. "snake case user id", or a
+/// change of case for the next word, e.g. "cap john"
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CaseStyle {
+    Camel,
+    Pascal,
+    Snake,
+    Kebab,
+    ScreamingSnake,
+    /// "cap": first letter uppercase, the rest as said
+    Capitalized,
+    /// "all caps"
+    Upper,
+    /// "no caps"
+    Lower,
+}
+
+/// Spoken names of the styles, longest first so "screaming snake case" isn't
+/// taken for "snake case"
+const STYLES: &[(&[&str], CaseStyle)] = &[
+    (&["screaming", "snake", "case"], CaseStyle::ScreamingSnake),
+    (&["constant", "case"], CaseStyle::ScreamingSnake),
+    (&["camel", "case"], CaseStyle::Camel),
+    (&["pascal", "case"], CaseStyle::Pascal),
+    (&["snake", "case"], CaseStyle::Snake),
+    (&["kebab", "case"], CaseStyle::Kebab),
+    (&["all", "caps"], CaseStyle::Upper),
+    (&["no", "caps"], CaseStyle::Lower),
+    (&["cap"], CaseStyle::Capitalized),
+];
+
+const OPEN: &[&str] = &["open", "bracket"];
+const CLOSE: &[&str] = &["close", "bracket"];
+
+impl CaseStyle {
+    /// Whether the style joins words into an identifier, rather than changing
+    /// their case only
+    fn is_identifier(self) -> bool {
+        !matches!(self, Self::Capitalized | Self::Upper | Self::Lower)
+    }
+
+    fn format(self, words: &[String]) -> String {
+        let capitalize = |word: &str| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect()
+            })
+        };
+        match self {
+            Self::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(index, word)| match index {
+                    0 => word.to_lowercase(),
+                    _ => capitalize(word),
+                })
+                .collect(),
+            Self::Pascal => words.iter().map(|word| capitalize(word)).collect(),
+            Self::Snake => lowercase(words).join("_"),
+            Self::Kebab => lowercase(words).join("-"),
+            Self::ScreamingSnake => words
+                .iter()
+                .map(|word| word.to_uppercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            Self::Capitalized => words
+                .iter()
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or_else(String::new, |first| {
+                        first.to_uppercase().chain(chars).collect()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            Self::Upper => words
+                .iter()
+                .map(|word| word.to_uppercase())
+                .collect::<Vec<_>>()
+                .join(" "),
+            Self::Lower => format!("{}{}", KEEP_CASE, lowercase(words).join(" ")),
+        }
+    }
+}
+
+fn lowercase(words: &[String]) -> Vec<String> {
+    words.iter().map(|word| word.to_lowercase()).collect()
+}
+
+/// Turn spoken case commands into identifiers and capitalized words
+///
+/// With `identifiers` (`case_commands`), "camel case open bracket foo bar
+/// close bracket" becomes `fooBar`; without "close bracket", the rest of the
+/// dictation is formatted. A style named without "open bracket" is just
+/// talked about ("snake case code") and kept. With `capitalization`
+/// (`capitalization_commands`), "cap", "all caps" and "no caps" change the
+/// next word only, or the words in brackets. Commas and periods
+/// Whisper adds between the command words are ignored, while punctuation
+/// after the last word is kept.
+pub fn apply(text: &str, identifiers: bool, capitalization: bool) -> String {
+    let styles: Vec<&(&[&str], CaseStyle)> = STYLES
+        .iter()
+        .filter(|(_, style)| {
+            if style.is_identifier() {
+                identifiers
+            } else {
+                capitalization
+            }
+        })
+        .collect();
+    let tokens = tokenizer::tokenize(text);
+    let mentioned = (0..tokens.len()).any(|index| {
+        styles
+            .iter()
+            .any(|(phrase, _)| says(&tokens, index, phrase))
+    });
+    if !mentioned {
+        // Left untouched, spacing included
+        return text.to_string();
+    }
+
+    let mut out = Rewriter::new(text);
+    let mut index = 0;
+    while index < tokens.len() {
+        let Some((phrase, style)) = styles
+            .iter()
+            .find(|(phrase, _)| says(&tokens, index, phrase))
+        else {
+            out.keep(&tokens[index..=index]);
+            index += 1;
+            continue;
+        };
+
+        let start = index + phrase.len();
+        if style.is_identifier() && !says(&tokens, start, OPEN) {
+            // "snake case code": the style is only mentioned
+            out.keep(&tokens[index..=index]);
+            index += 1;
+            continue;
+        }
+        let (span, next) = if says(&tokens, start, OPEN) {
+            let start = start + OPEN.len();
+            match (start..tokens.len()).find(|&close| says(&tokens, close, CLOSE)) {
+                Some(close) => (start..close, close + CLOSE.len()),
+                None => (start..tokens.len(), tokens.len()),
+            }
+        } else {
+            // "cap john": the next word only
+            let end = (start + 1).min(tokens.len());
+            (start..end, end)
+        };
+
+        let words: Vec<String> = if style.is_identifier() {
+            tokens[span]
+                .iter()
+                .flat_map(|token| {
+                    token
+                        .text
+                        .split(|c: char| !c.is_alphanumeric())
+                        .filter(|word| !word.is_empty())
+                        .map(str::to_string)
+                })
+                .collect()
+        } else {
+            tokens[span]
+                .iter()
+                .map(|token| token.text.trim_matches(|c: char| !c.is_alphanumeric()))
+                .filter(|word| !word.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        if words.is_empty() && !style.is_identifier() {
+            // "a bottle cap": not a command
+            out.keep(&tokens[index..next.max(start)]);
+            index = next.max(start);
+            continue;
+        }
+        let formatted = if words.is_empty() {
+            String::new()
+        } else {
+            let trailing = tokens[next - 1].trailing_punctuation();
+            format!("{}{}", style.format(&words), trailing)
+        };
+        out.replace(&tokens[index..next], &formatted);
+        index = next;
+    }
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_commands() {
+        let apply = |text: &str| super::apply(text, true, true);
+        assert_eq!(
+            apply("camel case open bracket foo bar close bracket"),
+            "fooBar"
+        );
+        assert_eq!(
+            apply("Rename it to Pascal case, open bracket, user profile, close bracket."),
+            "Rename it to UserProfile."
+        );
+        assert_eq!(
+            apply("call snake case open bracket get user id close bracket"),
+            "call get_user_id"
+        );
+        assert_eq!(apply("kebab case open bracket main menu"), "main-menu");
+        assert_eq!(
+            apply("set screaming snake case open bracket max retries close bracket to three"),
+            "set MAX_RETRIES to three"
+        );
+        assert_eq!(
+            apply("constant case open bracket api-key close bracket"),
+            "API_KEY"
+        );
+
+        // Styles talked about rather than used, or turned off
+        assert_eq!(
+            apply("I wrote snake case code yesterday and it was fine"),
+            "I wrote snake case code yesterday and it was fine"
+        );
+        assert_eq!(
+            apply("camel case is popular in java period"),
+            "camel case is popular in java period"
+        );
+        assert_eq!(
+            super::apply("snake case open bracket user id close bracket", false, true),
+            "snake case open bracket user id close bracket"
+        );
+
+        assert_eq!(apply("ask cap john about it"), "ask John about it");
+        assert_eq!(apply("All caps, warning! Hot"), "WARNING! Hot");
+        assert_eq!(
+            apply("cap open bracket new york close bracket is big"),
+            "New York is big"
+        );
+        assert_eq!(apply("No caps iPhone"), format!("{}iphone", KEEP_CASE));
+        assert_eq!(apply("a bottle cap"), "a bottle cap");
+
+        // Only the formatted words change; new lines and spacing are kept
+        assert_eq!(
+            apply("Dear team,\nthe  cap john file\n\nsnake case open bracket user id"),
+            "Dear team,\nthe  John file\n\nuser_id"
+        );
+
+        // Nothing to format, or no command
+        assert_eq!(apply("camel case open bracket close bracket"), "");
+        assert_eq!(apply("a  snake  study"), "a  snake  study");
+
+        // Without capitalization commands, everyday uses of the words are kept
+        for speech in [
+            "put the cap on the bottle",
+            "we need to cap spending",
+            "type it in all caps please",
+            "there are no caps on spending",
+        ] {
+            assert_eq!(super::apply(speech, true, false), speech);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::control;
+use crate::transcript_format::TranscriptFormat;
+use anyhow::Result;
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+Usage: dictator [COMMAND]
+
+Commands:
+  (none) [--log-format text|json] [--verbose]
+                                    Run the transcription daemon. JSON logs can also be
+                                    selected with DICTATOR_LOG_FORMAT=json. --verbose
+                                    draws an input level meter while recording.
+  config init [--annotated] [--force]
+                                    Write a default config file. --annotated writes a
+                                    commented config.toml documenting every field.
+  stats                             Show per-stage latency statistics from the history
+  transcribe <FILE> [--format F]    Transcribe an audio file with the configured backend
+                                    and print the processed text. --format srt, vtt or
+                                    json prints subtitles or the timed segments instead.
+  batch <DIR> [--jobs N] [--force] [--format F]
+                                    Transcribe every audio file under DIR with the
+                                    configured backend, N at a time (default 4), into a
+                                    .txt (or .srt, .vtt, .json) next to each. Files with
+                                    one are skipped unless --force is given.
+  devices                           List input devices, for choosing input_device
+  keys                              Show the configured shortcuts and the trigger syntax
+  corrections                       Show recurring corrections suggested as word overrides
+  corrections accept <HEARD>        Add a suggested correction to word_overrides
+  toggle|press|release|cancel       Control the running daemon, e.g. from a compositor
+                                    binding for a mouse button. press/release start and
+                                    stop recording in hold mode.
+  pause                             Pause the current recording, or resume it
+  retry                             Transcribe the last recording again
+  clear_context                     Forget the recent dictations context_prompt uses
+  profile <NAME>                    Switch the running daemon to a profile
+  status                            Print the daemon's state, active profile and language
+  level                             Print the input level (RMS and peak dBFS) of the
+                                    running daemon as it records, for status bars
+  help                              Show this message";
+
+/// Files `dictator batch` transcribes at once without `--jobs`
+const DEFAULT_BATCH_JOBS: usize = 4;
+
+/// Format of the daemon's log output
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per event, with span fields, for log shippers
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => anyhow::bail!(
+                "Unknown log format {:?} (expected \"text\" or \"json\")",
+                other
+            ),
+        }
+    }
+}
+
+/// Command selected on the command line
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Run the daemon (default when no arguments are given)
+    Run {
+        log_format: Option<LogFormat>,
+        /// Draw an input level meter on the terminal while recording
+        verbose: bool,
+    },
+    /// Write a default config file
+    ConfigInit { annotated: bool, force: bool },
+    /// Print latency statistics from the history store
+    Stats,
+    /// Transcribe one audio file to stdout
+    Transcribe {
+        file: PathBuf,
+        format: TranscriptFormat,
+    },
+    /// Transcribe a directory of audio files
+    Batch {
+        dir: PathBuf,
+        /// Files transcribed at once
+        jobs: usize,
+        /// Transcribe files that already have a transcript again
+        force: bool,
+        format: TranscriptFormat,
+    },
+    /// Print the names of the connected input devices
+    Devices,
+    /// Print the configured triggers and trigger syntax
+    Keys,
+    /// Print recurring corrections
+    Corrections,
+    /// Turn a correction candidate into a word override
+    CorrectionsAccept { heard: String },
+    /// Send a command (including `retry`) to the running daemon's control socket
+    Control(String),
+    /// Follow the running daemon's input level
+    Level,
+    /// Print the running daemon's status
+    Status,
+    /// Print usage information
+    Help,
+}
+
+/// Parse command-line arguments (excluding the program name)
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command> {
+    let args: Vec<String> = args.into_iter().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [] => Ok(Command::Run {
+            log_format: None,
+            verbose: false,
+        }),
+        ["help" | "--help" | "-h"] => Ok(Command::Help),
+        [flag, ..] if flag.starts_with('-') => {
+            let mut log_format = None;
+            let mut verbose = false;
+            let mut flags = args.iter();
+            while let Some(flag) = flags.next() {
+                match *flag {
+                    "--log-format" => {
+                        let format = flags.next().ok_or_else(|| {
+                            anyhow::anyhow!("--log-format needs a value\n\n{}", USAGE)
+                        })?;
+                        log_format = Some(LogFormat::parse(format)?);
+                    }
+                    "--verbose" | "-v" => verbose = true,
+                    other => anyhow::bail!("Unknown option: {}\n\n{}", other, USAGE),
+                }
+            }
+            Ok(Command::Run {
+                log_format,
+                verbose,
+            })
+        }
+        [command] if control::COMMANDS.contains(command) => {
+            Ok(Command::Control(command.to_string()))
+        }
+        ["stats"] => Ok(Command::Stats),
+        ["transcribe", file, flags @ ..] => {
+            let mut format = TranscriptFormat::default();
+            let mut flags = flags.iter();
+            while let Some(flag) = flags.next() {
+                match *flag {
+                    "--format" => format = parse_format(flags.next())?,
+                    other => {
+                        anyhow::bail!("Unknown option for `transcribe`: {}\n\n{}", other, USAGE)
+                    }
+                }
+            }
+            Ok(Command::Transcribe {
+                file: PathBuf::from(file),
+                format,
+            })
+        }
+        ["batch", dir, flags @ ..] => {
+            let mut jobs = DEFAULT_BATCH_JOBS;
+            let mut force = false;
+            let mut format = TranscriptFormat::default();
+            let mut flags = flags.iter();
+            while let Some(flag) = flags.next() {
+                match *flag {
+                    "--format" => format = parse_format(flags.next())?,
+                    "--jobs" | "-j" => {
+                        jobs = flags
+                            .next()
+                            .and_then(|jobs| jobs.parse().ok())
+                            .filter(|jobs| *jobs > 0)
+                            .ok_or_else(|| {
+                                anyhow::anyhow!("--jobs needs a number above 0\n\n{}", USAGE)
+                            })?;
+                    }
+                    "--force" => force = true,
+                    other => anyhow::bail!("Unknown option for `batch`: {}\n\n{}", other, USAGE),
+                }
+            }
+            Ok(Command::Batch {
+                dir: PathBuf::from(dir),
+                jobs,
+                force,
+                format,
+            })
+        }
+        ["keys"] => Ok(Command::Keys),
+        ["devices"] => Ok(Command::Devices),
+        [control::LEVEL_COMMAND] => Ok(Command::Level),
+        [control::STATUS_COMMAND] => Ok(Command::Status),
+        [control::PROFILE_COMMAND, name] => Ok(Command::Control(format!(
+            "{} {}",
+            control::PROFILE_COMMAND,
+            name
+        ))),
+        ["corrections"] => Ok(Command::Corrections),
+        ["corrections", "accept", heard] => Ok(Command::CorrectionsAccept {
+            heard: heard.to_string(),
+        }),
+        ["config", "init", flags @ ..] => {
+            let mut annotated = false;
+            let mut force = false;
+            for flag in flags {
+                match *flag {
+                    "--annotated" => annotated = true,
+                    "--force" => force = true,
+                    other => {
+                        anyhow::bail!("Unknown option for `config init`: {}\n\n{}", other, USAGE)
+                    }
+                }
+            }
+            Ok(Command::ConfigInit { annotated, force })
+        }
+        _ => anyhow::bail!("Unknown command: {}\n\n{}", args.join(" "), USAGE),
+    }
+}
+
+/// The value of a `--format` option
+fn parse_format(value: Option<&&str>) -> Result<TranscriptFormat> {
+    let value = value.ok_or_else(|| anyhow::anyhow!("--format needs a value\n\n{}", USAGE))?;
+    TranscriptFormat::parse(value)
+}
+
+pub fn print_usage() {
+    println!("{}", USAGE);
+}
@@ -0,0 +1,140 @@
+use crate::text_injection::{Segment, normalize_phrase};
+use tokio::process::Command;
+
+/// Dictations collected in compose mode, waiting for the send phrase
+///
+/// Each dictation is kept as its own part so "scratch that" can drop the most
+/// recent one. Nothing is injected until the send phrase is spoken on its own.
+pub struct ComposeBuffer {
+    parts: Vec<Vec<Segment>>,
+    send_phrase: String,
+    scratch_phrase: String,
+}
+
+impl ComposeBuffer {
+    pub fn new(send_phrase: &str, scratch_phrase: &str) -> Self {
+        Self {
+            parts: Vec::new(),
+            send_phrase: normalize_phrase(send_phrase),
+            scratch_phrase: normalize_phrase(scratch_phrase),
+        }
+    }
+
+    /// Add one dictation to the buffer
+    ///
+    /// Returns everything collected so far, emptying the buffer, when the
+    /// dictation is the send phrase.
+    pub fn add(&mut self, segments: Vec<Segment>) -> Option<Vec<Segment>> {
+        let phrase = match segments.as_slice() {
+            [Segment::Text(text)] => normalize_phrase(text),
+            _ => String::new(),
+        };
+
+        if phrase == self.send_phrase {
+            return Some(join_parts(std::mem::take(&mut self.parts)));
+        }
+        if phrase == self.scratch_phrase {
+            self.parts.pop();
+        } else if !segments.is_empty() {
+            self.parts.push(segments);
+        }
+        None
+    }
+
+    /// Text of the buffer as it would be sent, with key actions shown inline
+    pub fn preview(&self) -> String {
+        self.parts
+            .iter()
+            .flatten()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.clone(),
+                Segment::Keys(combo) => format!("[{}]", combo),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// The dictations of the buffer as one, a space between neighbouring text
+///
+/// Each dictation is trimmed, and the paste modes that send keystrokes type
+/// each text segment on its own, so the space has to be part of the text.
+fn join_parts(parts: Vec<Vec<Segment>>) -> Vec<Segment> {
+    let mut joined: Vec<Segment> = Vec::new();
+    for segment in parts.into_iter().flatten() {
+        match (joined.last_mut(), segment) {
+            (_, Segment::Text(text)) if text.is_empty() => {}
+            (Some(Segment::Text(last)), Segment::Text(text)) => {
+                last.push(' ');
+                last.push_str(&text);
+            }
+            (_, segment) => joined.push(segment),
+        }
+    }
+    joined
+}
+
+/// Show the current buffer in a desktop notification via `notify-send`
+///
+/// The synchronous hint makes notification daemons that support it (GNOME,
+/// KDE, mako, dunst) replace the previous preview instead of stacking them.
+pub fn show_preview(buffer: &ComposeBuffer) {
+    let body = match buffer.preview() {
+        preview if preview.is_empty() => "(empty)".to_string(),
+        preview => preview,
+    };
+
+    tokio::task::spawn(async move {
+        let result = Command::new("notify-send")
+            .args([
+                "--app-name=Dictator",
+                "--hint=string:x-canonical-private-synchronous:dictator-compose",
+                "Compose",
+                &body,
+            ])
+            .status()
+            .await;
+        if let Err(e) = result {
+            tracing::debug!("Failed to show compose preview: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_injection::KeyCombo;
+
+    fn text(s: &str) -> Vec<Segment> {
+        vec![Segment::Text(s.to_string())]
+    }
+
+    #[test]
+    fn test_compose_buffer() {
+        let mut buffer = ComposeBuffer::new("send", "scratch that");
+
+        assert!(buffer.add(text("Dear Ana,")).is_none());
+        assert!(buffer.add(text("I'm running late.")).is_none());
+        assert!(buffer.add(text("Scratch that.")).is_none());
+        assert!(
+            buffer
+                .add(vec![
+                    Segment::Text("See you soon".to_string()),
+                    Segment::Keys(KeyCombo::parse("Return").unwrap()),
+                ])
+                .is_none()
+        );
+        assert_eq!(buffer.preview(), "Dear Ana, See you soon [Return]");
+
+        let sent = buffer.add(text("Send.")).unwrap();
+        assert_eq!(
+            sent,
+            vec![
+                Segment::Text("Dear Ana, See you soon".to_string()),
+                Segment::Keys(KeyCombo::parse("Return").unwrap()),
+            ]
+        );
+        assert!(buffer.preview().is_empty());
+        assert_eq!(buffer.add(text("send")).unwrap().len(), 0);
+    }
+}
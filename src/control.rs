@@ -0,0 +1,249 @@
+use crate::audio::InputLevel;
+use crate::shortcuts::ShortcutEvent;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, watch};
+
+/// Commands accepted on the control socket, as sent by `dictator <command>`
+pub const COMMANDS: &[&str] = &[
+    "toggle",
+    "press",
+    "release",
+    "cancel",
+    "pause",
+    "retry",
+    "clear_context",
+];
+
+/// Command that streams the input level instead of replying once
+pub const LEVEL_COMMAND: &str = "level";
+
+/// Command that replies with the daemon's [`Status`] instead of `ok`
+pub const STATUS_COMMAND: &str = "status";
+
+/// Command that switches profile, followed by the profile name
+pub const PROFILE_COMMAND: &str = "profile";
+
+/// What the daemon is doing, as reported by `dictator status`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Status {
+    pub state: &'static str,
+    pub profile: Option<String>,
+    pub language: Option<String>,
+    /// Recordings waiting for the service to come back (`offline_queue`)
+    pub queued: usize,
+    /// The background health check can't reach the transcription service
+    pub unreachable: bool,
+}
+
+impl Status {
+    /// One line for status bars, e.g. `idle profile=email language=en`
+    pub fn line(&self) -> String {
+        let mut line = self.state.to_string();
+        if let Some(profile) = &self.profile {
+            line.push_str(&format!(" profile={}", profile));
+        }
+        if let Some(language) = &self.language {
+            line.push_str(&format!(" language={}", language));
+        }
+        if self.queued > 0 {
+            line.push_str(&format!(" queued={}", self.queued));
+        }
+        if self.unreachable {
+            line.push_str(" backend=unreachable");
+        }
+        line
+    }
+}
+
+/// Path to the control socket (`$XDG_RUNTIME_DIR/dictator.sock`)
+pub fn socket_path() -> Result<PathBuf> {
+    let runtime_dir =
+        std::env::var("XDG_RUNTIME_DIR").context("XDG_RUNTIME_DIR environment variable not set")?;
+    Ok(PathBuf::from(runtime_dir).join("dictator.sock"))
+}
+
+fn parse_command(command: &str) -> Option<ShortcutEvent> {
+    match command {
+        "toggle" => Some(ShortcutEvent::Toggle),
+        "press" => Some(ShortcutEvent::RecordPressed),
+        "release" => Some(ShortcutEvent::RecordReleased),
+        "cancel" => Some(ShortcutEvent::Cancel),
+        "pause" => Some(ShortcutEvent::Pause),
+        "retry" => Some(ShortcutEvent::Retry),
+        "clear_context" => Some(ShortcutEvent::ClearContext),
+        _ => None,
+    }
+}
+
+/// Accept commands on the control socket and forward them as shortcut events
+///
+/// Lets anything that can run a command act as a trigger: compositor mouse
+/// button bindings, foot pedals mapped by a remapper, scripts. Each connection
+/// sends one command per line and gets `ok` or an error back.
+pub async fn listen(
+    tx: mpsc::Sender<ShortcutEvent>,
+    levels: watch::Receiver<InputLevel>,
+    status: watch::Receiver<Status>,
+    profiles: Vec<String>,
+) -> Result<()> {
+    let path = socket_path()?;
+    // A socket left behind by a previous run would make bind fail
+    if path.exists() {
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove stale control socket: {:?}", path))?;
+    }
+
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to bind control socket: {:?}", path))?;
+    tracing::info!("Listening for commands on {:?}", path);
+
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .context("Failed to accept control connection")?;
+
+        let tx = tx.clone();
+        let levels = levels.clone();
+        let status = status.clone();
+        let profiles = profiles.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, tx, levels, status, &profiles).await {
+                tracing::warn!("Control connection error: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    tx: mpsc::Sender<ShortcutEvent>,
+    mut levels: watch::Receiver<InputLevel>,
+    status: watch::Receiver<Status>,
+    profiles: &[String],
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let command = line.trim();
+        if command == LEVEL_COMMAND {
+            // One line per update until the client disconnects: RMS and peak in dBFS
+            while levels.changed().await.is_ok() {
+                let level = *levels.borrow_and_update();
+                let line = format!("{:.1} {:.1}\n", level.rms_dbfs(), level.peak_dbfs());
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+
+        let event = match command.split_once(' ') {
+            Some((PROFILE_COMMAND, name)) => Some(ShortcutEvent::SwitchProfile(name.to_string())),
+            _ => parse_command(command),
+        };
+        let reply = match event {
+            _ if command == STATUS_COMMAND => status.borrow().line(),
+            Some(ShortcutEvent::SwitchProfile(name)) if !profiles.contains(&name) => {
+                format!("error: profile {:?} is not defined in profiles", name)
+            }
+            Some(event) => {
+                tracing::debug!("Control command: {}", command);
+                tx.send(event).await.context("App is not running")?;
+                "ok".to_string()
+            }
+            None => format!(
+                "error: unknown command {:?} (expected one of {})",
+                command,
+                COMMANDS.join(", ")
+            ),
+        };
+        writer.write_all(format!("{}\n", reply).as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// Print the daemon's input level as it changes, until interrupted
+pub async fn follow_levels() -> Result<()> {
+    let stream = connect().await?;
+    let (reader, mut writer) = stream.into_split();
+    writer
+        .write_all(format!("{}\n", LEVEL_COMMAND).as_bytes())
+        .await
+        .context("Failed to send command")?;
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+async fn connect() -> Result<UnixStream> {
+    let path = socket_path()?;
+    UnixStream::connect(&path)
+        .await
+        .with_context(|| format!("Failed to connect to {:?}; is dictator running?", path))
+}
+
+/// Send one command to the running daemon and return its reply
+pub async fn request(command: &str) -> Result<String> {
+    let stream = connect().await?;
+    let (reader, mut writer) = stream.into_split();
+    writer
+        .write_all(format!("{}\n", command).as_bytes())
+        .await
+        .context("Failed to send command")?;
+    writer.shutdown().await?;
+
+    let reply = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .unwrap_or_default();
+    match reply.strip_prefix("error: ") {
+        Some(error) => anyhow::bail!("{}", error),
+        None => Ok(reply),
+    }
+}
+
+/// Send one command to the running daemon
+pub async fn send(command: &str) -> Result<()> {
+    request(command).await.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_command_parses() {
+        for command in COMMANDS {
+            assert!(parse_command(command).is_some(), "{}", command);
+        }
+        assert_eq!(parse_command("stop"), None);
+    }
+
+    #[test]
+    fn test_status_line() {
+        let mut status = Status {
+            state: "idle",
+            ..Default::default()
+        };
+        assert_eq!(status.line(), "idle");
+        status.profile = Some("email".to_string());
+        status.language = Some("en".to_string());
+        assert_eq!(status.line(), "idle profile=email language=en");
+        status.queued = 2;
+        assert_eq!(status.line(), "idle profile=email language=en queued=2");
+        status.unreachable = true;
+        assert_eq!(
+            status.line(),
+            "idle profile=email language=en queued=2 backend=unreachable"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::history;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Candidates seen at least this many times are suggested as word overrides
+pub const MIN_OCCURRENCES: u32 = 2;
+
+/// Longest phrase, in words, considered a substitution rather than a rewrite
+const MAX_PHRASE_WORDS: usize = 3;
+
+/// A replacement the user made when correcting injected text
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Candidate {
+    /// Lowercased phrase as it was injected
+    pub original: String,
+    /// Phrase the user replaced it with
+    pub replacement: String,
+    /// How many corrections contained this substitution
+    pub count: u32,
+}
+
+/// Path to the candidate store (`$XDG_STATE_HOME/dictator/corrections.json`)
+pub fn corrections_path() -> Result<PathBuf> {
+    Ok(history::state_dir()?.join("corrections.json"))
+}
+
+pub fn load() -> Result<Vec<Candidate>> {
+    let path = corrections_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read corrections file: {:?}", path))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse corrections file: {:?}", path))
+}
+
+pub fn save(candidates: &[Candidate]) -> Result<()> {
+    let path = corrections_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create state directory: {:?}", parent))?;
+    }
+
+    let contents =
+        serde_json::to_string_pretty(candidates).context("Failed to serialize corrections")?;
+    std::fs::write(&path, contents)
+        .with_context(|| format!("Failed to write corrections file: {:?}", path))
+}
+
+/// Count each substitution found in a correction, returning how many were found
+pub fn record(injected: &str, corrected: &str) -> Result<usize> {
+    let substitutions = substitutions(injected, corrected);
+    if substitutions.is_empty() {
+        return Ok(0);
+    }
+
+    let mut candidates = load()?;
+    for (original, replacement) in &substitutions {
+        match candidates
+            .iter_mut()
+            .find(|c| &c.original == original && &c.replacement == replacement)
+        {
+            Some(candidate) => candidate.count += 1,
+            None => candidates.push(Candidate {
+                original: original.clone(),
+                replacement: replacement.clone(),
+                count: 1,
+            }),
+        }
+    }
+    save(&candidates)?;
+
+    Ok(substitutions.len())
+}
+
+/// Remove the candidate for `original`, returning it
+pub fn take(original: &str) -> Result<Candidate> {
+    let mut candidates = load()?;
+    let original = original.to_lowercase();
+    let index = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.original == original)
+        .max_by_key(|(_, c)| c.count)
+        .map(|(index, _)| index)
+        .with_context(|| format!("No correction candidate for {:?}", original))?;
+
+    let candidate = candidates.remove(index);
+    save(&candidates)?;
+    Ok(candidate)
+}
+
+/// Render suggested overrides for `dictator corrections`
+pub fn format_candidates(candidates: &[Candidate]) -> String {
+    let mut suggested: Vec<&Candidate> = candidates
+        .iter()
+        .filter(|c| c.count >= MIN_OCCURRENCES)
+        .collect();
+    if suggested.is_empty() {
+        return format!(
+            "No recurring corrections yet ({} seen once).",
+            candidates.len()
+        );
+    }
+    suggested.sort_by_key(|c| std::cmp::Reverse(c.count));
+
+    let mut out = format!("{:<30}{:<30}{:>6}\n", "heard", "corrected to", "times");
+    for candidate in suggested {
+        out.push_str(&format!(
+            "{:<30}{:<30}{:>6}\n",
+            candidate.original, candidate.replacement, candidate.count
+        ));
+    }
+    out.push_str("\nAccept one with: dictator corrections accept \"<heard>\"");
+    out
+}
+
+/// Word-level substitutions turning `injected` into `corrected`
+///
+/// Words are aligned on their longest common subsequence (ignoring case and
+/// surrounding punctuation). Each unaligned stretch that replaces a short
+/// phrase with another is a substitution; insertions, deletions and longer
+/// rewrites are edits rather than misrecognitions and are skipped.
+pub fn substitutions(injected: &str, corrected: &str) -> Vec<(String, String)> {
+    let old: Vec<&str> = injected.split_whitespace().collect();
+    let new: Vec<&str> = corrected.split_whitespace().collect();
+    let old_keys: Vec<String> = old.iter().map(|w| normalize_word(w)).collect();
+    let new_keys: Vec<String> = new.iter().map(|w| normalize_word(w)).collect();
+
+    // lcs[i][j] = length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old_keys[i] == new_keys[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut found = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut gap_i, mut gap_j) = (0, 0);
+    loop {
+        let at_end = i == old.len() || j == new.len();
+        if at_end || old_keys[i] == new_keys[j] {
+            let (end_i, end_j) = if at_end {
+                (old.len(), new.len())
+            } else {
+                (i, j)
+            };
+            let removed = &old_keys[gap_i..end_i];
+            let added = &new[gap_j..end_j];
+            if !removed.is_empty()
+                && !added.is_empty()
+                && removed.len() <= MAX_PHRASE_WORDS
+                && added.len() <= MAX_PHRASE_WORDS
+            {
+                let replacement: Vec<&str> = added.iter().map(|w| trim_word(w)).collect();
+                found.push((removed.join(" "), replacement.join(" ")));
+            }
+            if at_end {
+                break;
+            }
+            i += 1;
+            j += 1;
+            (gap_i, gap_j) = (i, j);
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    found
+}
+
+fn trim_word(word: &str) -> &str {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+}
+
+fn normalize_word(word: &str) -> String {
+    trim_word(word).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitutions() {
+        assert_eq!(
+            substitutions(
+                "Send the jason file to open ai.",
+                "Send the JSON file to OpenAI."
+            ),
+            vec![
+                ("jason".to_string(), "JSON".to_string()),
+                ("open ai".to_string(), "OpenAI".to_string()),
+            ]
+        );
+
+        // Punctuation and case fixes alone aren't substitutions
+        assert!(substitutions("hello world", "Hello, world!").is_empty());
+        // Pure insertions and deletions are edits, not misrecognitions
+        assert!(substitutions("see you soon", "see you very soon").is_empty());
+        assert!(substitutions("see you very soon", "see you soon").is_empty());
+    }
+}
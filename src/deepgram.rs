@@ -0,0 +1,161 @@
+use crate::audio::RecordedAudio;
+use crate::transcription::{RequestExtras, Transcript, TranscriptionConfig};
+use anyhow::{Context, Result};
+use std::time::Duration;
+
+/// Deepgram's prerecorded transcription endpoint
+const LISTEN_URL: &str = "https://api.deepgram.com/v1/listen";
+
+/// Deepgram's prerecorded audio API (`transcription_backend = "deepgram"`)
+///
+/// The whole recording is uploaded in the request body, like the OpenAI API,
+/// but long clips come back considerably faster than from a Whisper server.
+#[derive(Clone)]
+pub struct DeepgramClient {
+    http: reqwest::Client,
+    api_key: String,
+    extras: RequestExtras,
+}
+
+impl DeepgramClient {
+    pub fn new(api_key: &str, http: reqwest::Client, extras: RequestExtras) -> Self {
+        Self {
+            http,
+            api_key: api_key.to_string(),
+            extras,
+        }
+    }
+
+    pub async fn transcribe(
+        &self,
+        audio: &RecordedAudio,
+        config: &TranscriptionConfig,
+    ) -> Result<Transcript> {
+        tracing::info!("Transcribing {:?} with Deepgram", audio);
+
+        // Deepgram detects the container from the bytes themselves
+        let body = match audio {
+            RecordedAudio::File(file) => tokio::fs::read(file.path())
+                .await
+                .with_context(|| format!("Failed to read recording {:?}", file.path()))?,
+            RecordedAudio::Memory(bytes) => bytes.clone(),
+        };
+
+        let response = self
+            .http
+            .post(LISTEN_URL)
+            .query(&query(config))
+            .query(&self.extras.query)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("Token {}", self.api_key),
+            )
+            .headers(self.extras.headers.clone())
+            .body(body)
+            .send()
+            .await
+            .context("Deepgram API call failed")?;
+
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .context("Failed to read Deepgram response")?;
+        if !status.is_success() {
+            anyhow::bail!(
+                "Deepgram returned {}: {}",
+                status,
+                String::from_utf8_lossy(&body)
+            );
+        }
+
+        let transcript = transcript(&body)?;
+        tracing::info!("Transcription complete: {} chars", transcript.text.len());
+        Ok(transcript)
+    }
+
+    /// Check that the API accepts the key
+    pub async fn check_availability(&self) -> Result<()> {
+        tracing::debug!("Checking Deepgram availability...");
+
+        let response = self
+            .http
+            .get("https://api.deepgram.com/v1/projects")
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("Token {}", self.api_key),
+            )
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .context("Deepgram is unreachable")?;
+        if !response.status().is_success() {
+            anyhow::bail!("Deepgram rejected the api_key: {}", response.status());
+        }
+
+        tracing::debug!("Deepgram is available");
+        Ok(())
+    }
+}
+
+/// Query parameters for a request; each keyword is boosted separately
+fn query(config: &TranscriptionConfig) -> Vec<(&'static str, String)> {
+    let mut query = vec![
+        ("model", config.model.clone()),
+        ("smart_format", "true".to_string()),
+    ];
+    if config.language.is_empty() {
+        query.push(("detect_language", "true".to_string()));
+    } else {
+        query.push(("language", config.language.clone()));
+    }
+    for keyword in &config.keywords {
+        query.push(("keywords", keyword.clone()));
+    }
+    query
+}
+
+/// The text of the first alternative of the first channel, and the language
+/// Deepgram detected in it, if it was asked to
+fn transcript(body: &[u8]) -> Result<Transcript> {
+    let response: serde_json::Value =
+        serde_json::from_slice(body).context("Deepgram returned invalid JSON")?;
+    let text = response
+        .pointer("/results/channels/0/alternatives/0/transcript")
+        .and_then(|transcript| transcript.as_str())
+        .context("Deepgram response has no transcript")?;
+    let language = response
+        .pointer("/results/channels/0/detected_language")
+        .and_then(|language| language.as_str())
+        .map(str::to_string);
+    Ok(Transcript {
+        language,
+        ..Transcript::from_text(text.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deepgram_request_and_response() {
+        let config = TranscriptionConfig {
+            model: "nova-2".to_string(),
+            prompt: String::new(),
+            language: "en".to_string(),
+            keywords: vec!["jason".to_string(), "kubectl".to_string()],
+            translate: false,
+        };
+        let query = query(&config);
+        assert!(query.contains(&("language", "en".to_string())));
+        assert!(query.contains(&("keywords", "jason".to_string())));
+        assert!(query.contains(&("keywords", "kubectl".to_string())));
+
+        let body = br#"{"results":{"channels":[{"detected_language":"en","alternatives":[{"transcript":"hello world","confidence":0.98}]}]}}"#;
+        let parsed = transcript(body).unwrap();
+        assert_eq!(parsed.text, "hello world");
+        assert_eq!(parsed.language.as_deref(), Some("en"));
+        assert!(transcript(br#"{"results":{"channels":[]}}"#).is_err());
+    }
+}
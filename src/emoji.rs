@@ -0,0 +1,74 @@
+use crate::text_processing;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Built-in emoji commands, extended and overridden by `emoji_commands`
+///
+/// Most phrases end in "emoji" so they don't fire on ordinary speech; the
+/// "... face" phrases are rarely said otherwise.
+const EMOJI: &[(&str, &str)] = &[
+    ("thumbs up emoji", "👍"),
+    ("thumbs down emoji", "👎"),
+    ("smiley face", "🙂"),
+    ("smiley emoji", "🙂"),
+    ("grinning face", "😀"),
+    ("winking face", "😉"),
+    ("sad face", "🙁"),
+    ("crying face", "😢"),
+    ("thinking face", "🤔"),
+    ("laughing emoji", "😂"),
+    ("heart emoji", "❤️"),
+    ("fire emoji", "🔥"),
+    ("party emoji", "🎉"),
+    ("rocket emoji", "🚀"),
+    ("check mark emoji", "✅"),
+    ("cross mark emoji", "❌"),
+    ("eyes emoji", "👀"),
+    ("clapping emoji", "👏"),
+    ("praying emoji", "🙏"),
+    ("waving emoji", "👋"),
+    ("hundred emoji", "💯"),
+    ("star emoji", "⭐"),
+    ("sparkles emoji", "✨"),
+    ("warning emoji", "⚠️"),
+    ("shrug emoji", "🤷"),
+];
+
+/// The emoji commands, built-in ones merged with `extensions`
+///
+/// An extension with an empty emoji turns a built-in phrase off.
+pub fn commands(extensions: &HashMap<String, String>) -> Vec<(Regex, String)> {
+    let mut table: HashMap<String, String> = EMOJI
+        .iter()
+        .map(|(phrase, emoji)| (phrase.to_string(), emoji.to_string()))
+        .collect();
+    for (phrase, emoji) in extensions {
+        table.insert(phrase.trim().to_lowercase(), emoji.clone());
+    }
+    text_processing::phrase_commands(&table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emoji_commands() {
+        let extensions: HashMap<String, String> =
+            [("Ship It", "🚢"), ("thumbs up", "+1"), ("sad face", "")]
+                .into_iter()
+                .map(|(phrase, emoji)| (phrase.to_string(), emoji.to_string()))
+                .collect();
+        let commands = commands(&extensions);
+        let apply = |text: &str| {
+            commands.iter().fold(text.to_string(), |text, (re, emoji)| {
+                re.replace_all(&text, emoji.as_str()).to_string()
+            })
+        };
+
+        assert_eq!(apply("Great, Thumbs up emoji"), "Great, 👍");
+        assert_eq!(apply("ship it fire emoji"), "🚢 🔥");
+        assert_eq!(apply("thumbs up"), "+1");
+        assert_eq!(apply("sad face"), "sad face");
+    }
+}
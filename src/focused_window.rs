@@ -0,0 +1,73 @@
+use serde_json::Value;
+use std::process::Command;
+
+/// The window that currently has keyboard focus
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FocusedWindow {
+    /// Wayland app_id (or X11 class for XWayland windows)
+    pub app_id: String,
+    pub title: String,
+}
+
+/// Query the compositor for the focused window
+///
+/// Wayland has no portable API for this, so compositor-specific IPC is used:
+/// `hyprctl` on Hyprland and `swaymsg` on Sway. Returns `None` on other
+/// compositors or if the query fails.
+pub async fn current() -> Option<FocusedWindow> {
+    tokio::task::spawn_blocking(query).await.ok().flatten()
+}
+
+fn query() -> Option<FocusedWindow> {
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        let json = run_json("hyprctl", &["activewindow", "-j"])?;
+        return Some(FocusedWindow {
+            app_id: json["class"].as_str().unwrap_or_default().to_string(),
+            title: json["title"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+
+    if std::env::var_os("SWAYSOCK").is_some() {
+        let tree = run_json("swaymsg", &["-t", "get_tree"])?;
+        let node = find_focused(&tree)?;
+        let app_id = node["app_id"]
+            .as_str()
+            .or_else(|| node["window_properties"]["class"].as_str())
+            .unwrap_or_default();
+        return Some(FocusedWindow {
+            app_id: app_id.to_string(),
+            title: node["name"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+
+    tracing::debug!("Focused window lookup is not supported on this compositor");
+    None
+}
+
+fn run_json(program: &str, args: &[&str]) -> Option<Value> {
+    let output = Command::new(program).args(args).output();
+    match output {
+        Ok(output) if output.status.success() => serde_json::from_slice(&output.stdout).ok(),
+        Ok(output) => {
+            tracing::debug!("{} exited with {}", program, output.status);
+            None
+        }
+        Err(e) => {
+            tracing::debug!("Failed to run {}: {}", program, e);
+            None
+        }
+    }
+}
+
+/// Depth-first search of a sway tree for the focused leaf
+fn find_focused(node: &Value) -> Option<&Value> {
+    if node["focused"].as_bool() == Some(true) {
+        return Some(node);
+    }
+
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+        .find_map(find_focused)
+}
@@ -0,0 +1,188 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Time spent in each pipeline stage of one dictation, in milliseconds
+///
+/// Upload and server-side decoding happen in a single API request, so they are
+/// reported together as `transcription_ms`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LatencyBreakdown {
+    /// Stopping capture and finalizing the audio file
+    pub finalize_ms: u64,
+    /// Uploading the audio and waiting for the transcript
+    pub transcription_ms: u64,
+    /// Applying word overrides and voice commands
+    pub processing_ms: u64,
+    /// Copying to the clipboard and triggering paste
+    pub injection_ms: u64,
+    /// From the stop shortcut to injected text, including feedback sounds
+    pub total_ms: u64,
+}
+
+impl LatencyBreakdown {
+    /// Stage names and durations, in pipeline order
+    pub fn stages(&self) -> [(&'static str, u64); 5] {
+        [
+            ("finalize", self.finalize_ms),
+            ("transcription", self.transcription_ms),
+            ("processing", self.processing_ms),
+            ("injection", self.injection_ms),
+            ("total", self.total_ms),
+        ]
+    }
+}
+
+/// Milliseconds elapsed since `start`, for filling in a `LatencyBreakdown`
+pub fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+/// One completed dictation, as stored in the history file
+///
+/// Only metadata is stored; transcripts themselves are never written to disk.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    /// Unix timestamp (seconds) when processing finished
+    pub timestamp: u64,
+    pub chars: usize,
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Language the dictation was in, whether configured or detected
+    #[serde(default)]
+    pub language: Option<String>,
+    pub latency: LatencyBreakdown,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        chars: usize,
+        profile: Option<String>,
+        language: Option<String>,
+        latency: LatencyBreakdown,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            timestamp,
+            chars,
+            profile,
+            language,
+            latency,
+        }
+    }
+}
+
+/// Directory for dictator's state files (`$XDG_STATE_HOME/dictator`)
+pub fn state_dir() -> Result<PathBuf> {
+    let state_home = if let Ok(dir) = std::env::var("XDG_STATE_HOME") {
+        PathBuf::from(dir)
+    } else {
+        let home = std::env::var("HOME").context("HOME environment variable not set")?;
+        PathBuf::from(home).join(".local").join("state")
+    };
+
+    Ok(state_home.join("dictator"))
+}
+
+/// Path to the history file (`$XDG_STATE_HOME/dictator/history.jsonl`)
+pub fn history_path() -> Result<PathBuf> {
+    Ok(state_dir()?.join("history.jsonl"))
+}
+
+/// Append an entry to the history file, creating it if needed
+pub fn append(entry: &HistoryEntry) -> Result<()> {
+    let path = history_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create state directory: {:?}", parent))?;
+    }
+
+    let mut line = serde_json::to_string(entry).context("Failed to serialize history entry")?;
+    line.push('\n');
+
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("Failed to append to history file: {:?}", path))
+}
+
+/// Load all entries, skipping lines that fail to parse
+pub fn load() -> Result<Vec<HistoryEntry>> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read history file: {:?}", path))?;
+
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Render per-stage latency statistics for `dictator stats`
+pub fn format_stats(entries: &[HistoryEntry]) -> String {
+    if entries.is_empty() {
+        return "No dictations recorded yet.".to_string();
+    }
+
+    let mut out = format!(
+        "{} dictations, {} characters\n\n{:<15}{:>10}{:>10}{:>10}{:>10}\n",
+        entries.len(),
+        entries.iter().map(|e| e.chars).sum::<usize>(),
+        "stage (ms)",
+        "mean",
+        "median",
+        "p95",
+        "max"
+    );
+
+    let stage_names = LatencyBreakdown::default().stages().map(|(name, _)| name);
+    for (index, name) in stage_names.into_iter().enumerate() {
+        let mut values: Vec<u64> = entries
+            .iter()
+            .map(|e| e.latency.stages()[index].1)
+            .collect();
+        values.sort_unstable();
+
+        let mean = values.iter().sum::<u64>() / values.len() as u64;
+        let percentile = |p: usize| values[(values.len() - 1) * p / 100];
+        out.push_str(&format!(
+            "{:<15}{:>10}{:>10}{:>10}{:>10}\n",
+            name,
+            mean,
+            percentile(50),
+            percentile(95),
+            values[values.len() - 1]
+        ));
+    }
+
+    // Most used first
+    let mut languages: Vec<(&str, usize)> = Vec::new();
+    for language in entries.iter().filter_map(|e| e.language.as_deref()) {
+        match languages.iter_mut().find(|(name, _)| *name == language) {
+            Some((_, count)) => *count += 1,
+            None => languages.push((language, 1)),
+        }
+    }
+    if !languages.is_empty() {
+        languages.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let counts: Vec<String> = languages
+            .iter()
+            .map(|(language, count)| format!("{} {}", language, count))
+            .collect();
+        out.push_str(&format!("\nLanguages: {}\n", counts.join(", ")));
+    }
+
+    out
+}
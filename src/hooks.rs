@@ -1,5 +1,26 @@
+use crate::text_injection::normalize_phrase;
+use std::collections::HashMap;
 use tokio::process::Command;
 
+/// Find the voice command whose phrase matches the whole transcript
+///
+/// Matching ignores case, extra whitespace and trailing punctuation, so
+/// "Open terminal." matches the phrase "open terminal".
+pub fn find_voice_command<'a>(
+    commands: &'a HashMap<String, String>,
+    transcript: &str,
+) -> Option<(&'a str, &'a str)> {
+    let spoken = normalize_phrase(transcript);
+    if spoken.is_empty() {
+        return None;
+    }
+
+    commands
+        .iter()
+        .find(|(phrase, _)| normalize_phrase(phrase) == spoken)
+        .map(|(phrase, command)| (phrase.as_str(), command.as_str()))
+}
+
 pub fn run_hook(label: &str, command: &str) {
     let label = label.to_owned();
     let command = command.to_owned();
@@ -33,3 +54,30 @@ pub fn run_hook(label: &str, command: &str) {
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_voice_command() {
+        let commands = HashMap::from([
+            ("open terminal".to_string(), "foot".to_string()),
+            (
+                "Lock screen".to_string(),
+                "loginctl lock-session".to_string(),
+            ),
+        ]);
+
+        assert_eq!(
+            find_voice_command(&commands, " Open  terminal."),
+            Some(("open terminal", "foot"))
+        );
+        assert_eq!(
+            find_voice_command(&commands, "lock screen!"),
+            Some(("Lock screen", "loginctl lock-session"))
+        );
+        assert_eq!(find_voice_command(&commands, "open terminal please"), None);
+        assert_eq!(find_voice_command(&commands, ""), None);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::transcription::{Transcript, TranscriptSegment, TranscriptionConfig};
+use anyhow::{Context, Result};
+use std::path::Path;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+/// whisper.cpp only accepts 16 kHz audio
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// A whisper.cpp model loaded into memory (`transcription_backend = "local"`)
+///
+/// Audio never leaves the machine. Transcription is CPU-bound and runs on a
+/// blocking thread; each one gets its own decoder state, so the model is
+/// shared rather than reloaded.
+pub struct LocalWhisper {
+    context: WhisperContext,
+}
+
+impl LocalWhisper {
+    /// Load the model; this takes a few seconds for the larger models
+    pub fn load(model_path: &Path) -> Result<Self> {
+        let path = model_path
+            .to_str()
+            .context("model_path is not valid UTF-8")?;
+        let context = WhisperContext::new_with_params(path, WhisperContextParameters::default())
+            .with_context(|| format!("Failed to load whisper model {:?}", model_path))?;
+
+        tracing::info!("Loaded local whisper model {:?}", model_path);
+        Ok(Self { context })
+    }
+
+    /// Transcribe 16 kHz mono samples, e.g. from `decode::decode_wav`
+    pub fn transcribe(&self, samples: &[f32], config: &TranscriptionConfig) -> Result<Transcript> {
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        // An empty language lets whisper detect it, like the API
+        params.set_language(Some(if config.language.is_empty() {
+            "auto"
+        } else {
+            &config.language
+        }));
+        if !config.prompt.is_empty() {
+            params.set_initial_prompt(&config.prompt);
+        }
+        params.set_translate(config.translate);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_special(false);
+        params.set_print_timestamps(false);
+
+        let mut state = self
+            .context
+            .create_state()
+            .context("Failed to create whisper state")?;
+        state
+            .full(params, samples)
+            .context("Local transcription failed")?;
+
+        let segments = state
+            .full_n_segments()
+            .context("Failed to read transcription")?;
+        let mut transcript = Transcript::default();
+        for segment in 0..segments {
+            let text = state
+                .full_get_segment_text(segment)
+                .context("Failed to read transcription segment")?;
+            transcript.text.push_str(&text);
+            // Timestamps are in hundredths of a second
+            transcript.segments.push(TranscriptSegment {
+                start: state.full_get_segment_t0(segment).unwrap_or_default() as f32 / 100.0,
+                end: state.full_get_segment_t1(segment).unwrap_or_default() as f32 / 100.0,
+                text,
+                ..TranscriptSegment::default()
+            });
+        }
+        transcript.text = transcript.text.trim().to_string();
+        if config.language.is_empty() && !config.translate {
+            transcript.language = state
+                .full_lang_id_from_state()
+                .ok()
+                .and_then(whisper_rs::get_lang_str)
+                .map(str::to_string);
+        }
+
+        tracing::info!("Transcription complete: {} chars", transcript.text.len());
+        Ok(transcript)
+    }
+}
@@ -1,21 +1,171 @@
+mod announce;
+mod api_upload;
 mod app;
+mod archive;
+mod assets;
 mod audio;
+mod batch;
+mod case_commands;
+mod cli;
+mod compose;
 mod config;
+mod control;
+mod corrections;
+mod deepgram;
+mod emoji;
+mod focused_window;
+mod history;
 mod hooks;
+#[cfg(feature = "local")]
+mod local_transcription;
+mod numbers;
+mod offline_queue;
+mod prompt;
+mod remote_desktop;
+mod scratch;
 mod shortcuts;
+mod snippets;
+mod spelling;
 mod text_injection;
 mod text_processing;
+mod tokenizer;
+mod transcript_format;
 mod transcription;
+#[cfg(feature = "vosk")]
+mod vosk_transcription;
+#[cfg(feature = "websocket")]
+mod websocket_transcription;
 
 use app::App;
+use audio::AudioCapture;
+use cli::{Command, LogFormat};
 use config::Config;
 
 use anyhow::Result;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+fn main() -> Result<()> {
+    // SAFETY: called before the tokio runtime starts any threads
+    unsafe { audio::capture::set_stream_properties() };
+    async_main()
+}
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
+async fn async_main() -> Result<()> {
+    let command = cli::parse(std::env::args().skip(1))?;
+    init_logging(&command)?;
+
+    match command {
+        Command::Run { verbose, .. } => run_daemon(verbose).await,
+        Command::ConfigInit { annotated, force } => {
+            let path = Config::init(annotated, force)?;
+            println!("Wrote config to {}", path.display());
+            Ok(())
+        }
+        Command::Stats => {
+            println!("{}", history::format_stats(&history::load()?));
+            Ok(())
+        }
+        Command::Transcribe { file, format } => {
+            let config = Config::load()?;
+            config.validate()?;
+            print!("{}", batch::transcribe_file(&config, &file, format).await?);
+            Ok(())
+        }
+        Command::Batch {
+            dir,
+            jobs,
+            force,
+            format,
+        } => {
+            let config = Config::load()?;
+            config.validate()?;
+            let summary = batch::run(&config, &dir, jobs, force, format).await?;
+            println!(
+                "{} transcribed, {} skipped, {} failed",
+                summary.transcribed, summary.skipped, summary.failed
+            );
+            if summary.failed > 0 {
+                anyhow::bail!("{} files failed to transcribe", summary.failed);
+            }
+            Ok(())
+        }
+        Command::Devices => {
+            for name in AudioCapture::list_input_devices()? {
+                println!("{}", name);
+            }
+            Ok(())
+        }
+        Command::Keys => {
+            let config = Config::load()?;
+            let mut configured = vec![("shortcut".to_string(), &config.shortcut)];
+            if let Some(shortcut) = &config.correction_shortcut {
+                configured.push(("correction_shortcut".to_string(), shortcut));
+            }
+            if let Some(shortcut) = &config.cancel_shortcut {
+                configured.push(("cancel_shortcut".to_string(), shortcut));
+            }
+            for binding in &config.shortcut_bindings {
+                configured.push((format!("action:{}", binding.action), &binding.trigger));
+            }
+            println!("{}", shortcuts::format_key_help(&configured));
+            Ok(())
+        }
+        Command::Corrections => {
+            println!("{}", corrections::format_candidates(&corrections::load()?));
+            Ok(())
+        }
+        Command::CorrectionsAccept { heard } => {
+            let candidate = corrections::take(&heard)?;
+            let path = Config::add_word_override(&candidate.original, &candidate.replacement)?;
+            println!(
+                "Added word override {:?} -> {:?} to {}",
+                candidate.original,
+                candidate.replacement,
+                path.display()
+            );
+            Ok(())
+        }
+        Command::Control(command) => control::send(&command).await,
+        Command::Level => control::follow_levels().await,
+        Command::Status => {
+            println!("{}", control::request(control::STATUS_COMMAND).await?);
+            Ok(())
+        }
+        Command::Help => {
+            cli::print_usage();
+            Ok(())
+        }
+    }
+}
+
+/// Set up log output; `--log-format` takes precedence over DICTATOR_LOG_FORMAT
+fn init_logging(command: &Command) -> Result<()> {
+    let format = match command {
+        Command::Run {
+            log_format: Some(format),
+            ..
+        } => *format,
+        _ => match std::env::var("DICTATOR_LOG_FORMAT") {
+            Ok(value) => LogFormat::parse(&value)?,
+            Err(_) => LogFormat::default(),
+        },
+    };
+
+    // Commands other than the daemon print their results on stdout, which
+    // `dictator transcribe` output is piped from
+    let writer = match command {
+        Command::Run { .. } => BoxMakeWriter::new(std::io::stdout),
+        _ => BoxMakeWriter::new(std::io::stderr),
+    };
+    match format {
+        LogFormat::Text => tracing_subscriber::fmt().with_writer(writer).init(),
+        LogFormat::Json => tracing_subscriber::fmt().json().with_writer(writer).init(),
+    }
+    Ok(())
+}
 
+async fn run_daemon(verbose: bool) -> Result<()> {
     tracing::info!("Starting dictator voice transcription daemon");
 
     let config = Config::load()?;
@@ -27,6 +177,9 @@ async fn main() -> Result<()> {
     local
         .run_until(async move {
             let app = App::new(config).await?;
+            if verbose {
+                audio::level::spawn_terminal_meter(app.input_levels());
+            }
             app.run().await
         })
         .await
@@ -0,0 +1,308 @@
+use crate::tokenizer::{self, Rewriter, Token};
+
+const UNITS: &[&str] = &[
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const TENS: &[(&str, u64)] = &[
+    ("twenty", 20),
+    ("thirty", 30),
+    ("forty", 40),
+    ("fifty", 50),
+    ("sixty", 60),
+    ("seventy", 70),
+    ("eighty", 80),
+    ("ninety", 90),
+];
+
+const SCALES: &[(&str, u64)] = &[
+    ("thousand", 1_000),
+    ("million", 1_000_000),
+    ("billion", 1_000_000_000),
+];
+
+/// Denominators of simple fractions, singular and plural ("second" is left
+/// out, as in "one second")
+const DENOMINATORS: &[(&str, &str, u64)] = &[
+    ("half", "halves", 2),
+    ("third", "thirds", 3),
+    ("quarter", "quarters", 4),
+    ("fourth", "fourths", 4),
+    ("fifth", "fifths", 5),
+    ("sixth", "sixths", 6),
+    ("seventh", "sevenths", 7),
+    ("eighth", "eighths", 8),
+    ("ninth", "ninths", 9),
+    ("tenth", "tenths", 10),
+];
+
+/// What a number word is, for which words may follow it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    /// zero to nine
+    Unit,
+    /// ten to nineteen
+    Teen,
+    /// twenty, thirty, ...
+    Tens,
+    /// twenty-three, said as one word
+    TensUnit,
+    Hundred,
+    Scale(u64),
+}
+
+fn word(bare: &str) -> Option<(u64, Kind)> {
+    if let Some(value) = UNITS.iter().position(|name| *name == bare) {
+        let kind = if value < 10 { Kind::Unit } else { Kind::Teen };
+        return Some((value as u64, kind));
+    }
+    if let Some((_, value)) = TENS.iter().find(|(name, _)| *name == bare) {
+        return Some((*value, Kind::Tens));
+    }
+    if bare == "hundred" {
+        return Some((100, Kind::Hundred));
+    }
+    if let Some((_, scale)) = SCALES.iter().find(|(name, _)| *name == bare) {
+        return Some((*scale, Kind::Scale(*scale)));
+    }
+    // "twenty-three"
+    let (tens, unit) = bare.split_once('-')?;
+    match (word(tens)?, word(unit)?) {
+        ((tens, Kind::Tens), (unit, Kind::Unit)) if unit > 0 => Some((tens + unit, Kind::TensUnit)),
+        _ => None,
+    }
+}
+
+fn digit(token: &Token) -> Option<u64> {
+    UNITS[..10]
+        .iter()
+        .position(|name| *name == token.bare)
+        .map(|value| value as u64)
+}
+
+/// A whole number said from `start`: its value, and the index after it
+///
+/// Words only join when they make sense together, so "one two" is two
+/// numbers while "twenty three" is 23. Two two-digit groups are read as a
+/// year ("nineteen ninety nine"). A word followed by punctuation ends the
+/// number.
+fn integer(tokens: &[Token], start: usize) -> Option<(u64, usize)> {
+    let mut total = 0;
+    let mut current = 0;
+    let mut year: Option<u64> = None;
+    let mut last: Option<Kind> = None;
+    let mut index = start;
+
+    while let Some(token) = tokens.get(index) {
+        // "one hundred and five"
+        if token.bare == "and"
+            && matches!(last, Some(Kind::Hundred | Kind::Scale(_)))
+            && token.trailing_punctuation().is_empty()
+            && tokens
+                .get(index + 1)
+                .and_then(|next| word(&next.bare))
+                .is_some_and(|(_, kind)| {
+                    matches!(kind, Kind::Unit | Kind::Teen | Kind::Tens | Kind::TensUnit)
+                })
+        {
+            index += 1;
+            continue;
+        }
+
+        let Some((value, kind)) = word(&token.bare) else {
+            break;
+        };
+        let fits = match (last, kind) {
+            (None, _) => !matches!(kind, Kind::Hundred | Kind::Scale(_)),
+            (
+                Some(Kind::Unit | Kind::Teen | Kind::TensUnit),
+                Kind::Unit | Kind::Teen | Kind::Tens | Kind::TensUnit,
+            ) => false,
+            (Some(Kind::Tens), Kind::Unit) => value > 0,
+            (Some(Kind::Tens), Kind::Teen | Kind::Tens | Kind::TensUnit) => false,
+            (Some(Kind::Hundred), Kind::Hundred) => false,
+            (_, Kind::Hundred) => year.is_none() && (1..100).contains(&current),
+            (_, Kind::Scale(scale)) => {
+                year.is_none()
+                    && current > 0
+                    && !matches!(last, Some(Kind::Scale(previous)) if previous <= scale)
+                    && (total == 0 || total >= scale * 1000)
+            }
+            (Some(Kind::Hundred | Kind::Scale(_)), _) => value > 0,
+        };
+
+        // "nineteen ninety", "twenty twenty-four"
+        let starts_year = !fits
+            && year.is_none()
+            && total == 0
+            && (10..100).contains(&current)
+            && matches!(kind, Kind::Teen | Kind::Tens | Kind::TensUnit);
+        if starts_year {
+            year = Some(current * 100);
+            current = value;
+        } else if !fits {
+            break;
+        } else {
+            match kind {
+                Kind::Hundred => current *= 100,
+                Kind::Scale(scale) => {
+                    total += current * scale;
+                    current = 0;
+                }
+                _ => current += value,
+            }
+        }
+        last = Some(kind);
+        index += 1;
+        if !token.trailing_punctuation().is_empty() {
+            break;
+        }
+    }
+
+    (index > start).then(|| (year.unwrap_or_default() + total + current, index))
+}
+
+/// Write number words as digits (`convert_numbers`)
+///
+/// "twenty three" becomes `23`, "three point one four" `3.14`, "fifty
+/// percent" `50%`, "negative ten" `-10` and "two thirds" `2/3`. A single
+/// word from zero to nine is kept as a word ("one of them", "two people"),
+/// unless it's part of a decimal, percentage, fraction or negative number.
+pub fn convert(text: &str) -> String {
+    let tokens = tokenizer::tokenize(text);
+    if !tokens.iter().any(|token| word(&token.bare).is_some()) {
+        return text.to_string();
+    }
+
+    let mut out = Rewriter::new(text);
+    let mut index = 0;
+    while index < tokens.len() {
+        let negative =
+            tokens[index].bare == "negative" && tokens[index].trailing_punctuation().is_empty();
+        let start = index + usize::from(negative);
+        let Some((value, mut end)) = integer(&tokens, start) else {
+            out.keep(&tokens[index..=index]);
+            index += 1;
+            continue;
+        };
+
+        let ended = !tokens[end - 1].trailing_punctuation().is_empty();
+        let mut number = value.to_string();
+        let mut qualified = negative;
+        // "three point one four", each decimal said as a digit
+        if !ended
+            && tokens.get(end).is_some_and(|token| token.bare == "point")
+            && tokens.get(end + 1).and_then(digit).is_some()
+            && tokens[end].trailing_punctuation().is_empty()
+        {
+            number.push('.');
+            end += 1;
+            while let Some(decimal) = tokens.get(end).and_then(digit) {
+                number.push_str(&decimal.to_string());
+                end += 1;
+                if !tokens[end - 1].trailing_punctuation().is_empty() {
+                    break;
+                }
+            }
+            qualified = true;
+        }
+
+        let ended = !tokens[end - 1].trailing_punctuation().is_empty();
+        if !ended && let Some(next) = tokens.get(end) {
+            if next.bare == "percent" {
+                number.push('%');
+                end += 1;
+                qualified = true;
+            } else if !number.contains('.')
+                && let Some((_, _, denominator)) = DENOMINATORS
+                    .iter()
+                    .find(|(one, many, _)| next.bare == *one || next.bare == *many)
+            {
+                number = format!("{}/{}", value, denominator);
+                end += 1;
+                qualified = true;
+            }
+        }
+
+        if end - start == 1 && value < 10 && !qualified {
+            out.keep(&tokens[index..=index]);
+            index += 1;
+            continue;
+        }
+
+        let first = tokens[index].text;
+        let leading = &first[..first.len()
+            - first
+                .trim_start_matches(|c: char| !c.is_alphanumeric())
+                .len()];
+        let sign = if negative { "-" } else { "" };
+        let converted = format!(
+            "{}{}{}{}",
+            leading,
+            sign,
+            number,
+            tokens[end - 1].trailing_punctuation()
+        );
+        out.replace(&tokens[index..end], &converted);
+        index = end;
+    }
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_numbers() {
+        assert_eq!(convert("twenty three apples"), "23 apples");
+        assert_eq!(convert("Twenty-five."), "25.");
+        assert_eq!(
+            convert("one hundred and five thousand three hundred twelve"),
+            "105312"
+        );
+        assert_eq!(convert("two million four hundred thousand"), "2400000");
+        assert_eq!(convert("pi is three point one four"), "pi is 3.14");
+        assert_eq!(convert("about fifty percent, maybe"), "about 50%, maybe");
+        assert_eq!(convert("seven percent"), "7%");
+        assert_eq!(convert("it was negative ten outside"), "it was -10 outside");
+        assert_eq!(convert("two thirds of the votes"), "2/3 of the votes");
+        assert_eq!(convert("in nineteen ninety nine"), "in 1999");
+        assert_eq!(convert("(twenty twenty-four)"), "(2024)");
+        assert_eq!(
+            convert("Total:\n  twenty three\n\nthanks"),
+            "Total:\n  23\n\nthanks"
+        );
+
+        // Ambiguous: small numbers, pronouns and other meanings stay words
+        assert_eq!(convert("one of the two options"), "one of the two options");
+        assert_eq!(
+            convert("the point is, one second"),
+            "the point is, one second"
+        );
+        assert_eq!(convert("one two three"), "one two three");
+        assert_eq!(convert("rock and roll"), "rock and roll");
+        assert_eq!(convert("a hundred times"), "a hundred times");
+        assert_eq!(convert("twenty, thirty"), "20, 30");
+        assert_eq!(convert("the negative side"), "the negative side");
+    }
+}
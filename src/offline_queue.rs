@@ -0,0 +1,167 @@
+use crate::audio::RecordedAudio;
+use crate::history;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What is kept with a queued recording to transcribe it as dictated
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct QueuedDictation {
+    /// Unix timestamp (milliseconds) when the recording was queued
+    pub timestamp: u64,
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Language the recording was to be transcribed in, after app rules
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/// Keeps queued recordings on disk (`offline_queue`), so they survive a
+/// crash or reboot
+///
+/// Each recording is stored as `<timestamp>.<ext>` next to a `.json` with its
+/// [`QueuedDictation`], and removed once its transcript was delivered.
+pub struct QueueStore {
+    dir: PathBuf,
+}
+
+impl QueueStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The queue in `$XDG_STATE_HOME/dictator/queue`
+    pub fn open() -> Result<Self> {
+        Ok(Self::new(history::state_dir()?.join("queue")))
+    }
+
+    /// Store a recording, returning its path
+    pub fn save(&self, audio: &RecordedAudio, dictation: &QueuedDictation) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create queue directory: {:?}", self.dir))?;
+
+        let path = match audio {
+            RecordedAudio::File(file) => {
+                let extension = file.path().extension().unwrap_or_default();
+                let path = self.dir.join(format!(
+                    "{}.{}",
+                    dictation.timestamp,
+                    extension.to_string_lossy()
+                ));
+                std::fs::copy(file.path(), &path)
+                    .with_context(|| format!("Failed to queue recording to {:?}", path))?;
+                path
+            }
+            RecordedAudio::Memory(bytes) => {
+                let path = self.dir.join(format!("{}.wav", dictation.timestamp));
+                std::fs::write(&path, bytes)
+                    .with_context(|| format!("Failed to queue recording to {:?}", path))?;
+                path
+            }
+        };
+
+        let meta_path = path.with_extension("json");
+        let meta = serde_json::to_string(dictation).context("Failed to serialize queue entry")?;
+        std::fs::write(&meta_path, meta)
+            .with_context(|| format!("Failed to write queue entry {:?}", meta_path))?;
+        Ok(path)
+    }
+
+    /// The queued recordings, oldest first
+    ///
+    /// A recording whose `.json` is missing or unreadable was interrupted
+    /// while being queued, and is queued with default settings.
+    pub fn load(&self) -> Result<Vec<(PathBuf, QueuedDictation)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read queue directory: {:?}", self.dir))?;
+        let mut queued: Vec<(PathBuf, QueuedDictation)> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext != "json"))
+            .map(|path| {
+                let dictation = std::fs::read_to_string(path.with_extension("json"))
+                    .ok()
+                    .and_then(|meta| serde_json::from_str(&meta).ok())
+                    .unwrap_or_else(|| QueuedDictation {
+                        timestamp: timestamp_of(&path),
+                        ..Default::default()
+                    });
+                (path, dictation)
+            })
+            .collect();
+        queued.sort_by_key(|(_, dictation)| dictation.timestamp);
+        Ok(queued)
+    }
+
+    /// Forget a recording once it has been delivered
+    pub fn remove(&self, path: &Path) -> Result<()> {
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove queued recording {:?}", path))?;
+        let meta_path = path.with_extension("json");
+        if meta_path.exists() {
+            std::fs::remove_file(&meta_path)
+                .with_context(|| format!("Failed to remove queue entry {:?}", meta_path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Milliseconds since the Unix epoch, naming a newly queued recording
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// The timestamp a recording is named after
+fn timestamp_of(path: &Path) -> u64 {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.parse().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = QueueStore::new(dir.path().join("queue"));
+        assert!(store.load().unwrap().is_empty());
+
+        let later = QueuedDictation {
+            timestamp: 1_792_160_581_042,
+            profile: Some("email".to_string()),
+            language: Some("en".to_string()),
+        };
+        let earlier = QueuedDictation {
+            timestamp: 1_792_160_500_000,
+            ..Default::default()
+        };
+        let later_path = store
+            .save(&RecordedAudio::Memory(b"RIFF".to_vec()), &later)
+            .unwrap();
+        let earlier_path = store
+            .save(&RecordedAudio::Memory(b"RIFF".to_vec()), &earlier)
+            .unwrap();
+        assert_eq!(later_path.file_name().unwrap(), "1792160581042.wav");
+
+        // Interrupted while queueing: the recording made it, its settings didn't
+        std::fs::remove_file(earlier_path.with_extension("json")).unwrap();
+        assert_eq!(
+            store.load().unwrap(),
+            [(earlier_path, earlier), (later_path.clone(), later)]
+        );
+
+        store.remove(&later_path).unwrap();
+        assert_eq!(store.load().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+
+/// The last sentences dictated, fed back as the Whisper prompt (`context_prompt`)
+///
+/// Whisper continues the style of its prompt, so a multi-part dictation keeps
+/// the casing, names and terminology of the parts before it.
+pub struct RecentContext {
+    sentences: VecDeque<String>,
+    max_sentences: usize,
+    max_chars: usize,
+}
+
+impl RecentContext {
+    pub fn new(max_sentences: usize, max_chars: usize) -> Self {
+        Self {
+            sentences: VecDeque::new(),
+            max_sentences,
+            max_chars,
+        }
+    }
+
+    /// Remember the sentences of a dictation, forgetting the oldest ones
+    pub fn push(&mut self, text: &str) {
+        self.sentences.extend(sentences(text));
+        while self.sentences.len() > self.max_sentences {
+            self.sentences.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.sentences.clear();
+    }
+
+    /// The remembered sentences, cut at the front to `max_chars`
+    ///
+    /// Whisper gives most weight to the end of the prompt, so the latest
+    /// sentences are the ones kept.
+    pub fn prompt(&self) -> String {
+        let joined = Vec::from(self.sentences.clone()).join(" ");
+        tail(&joined, self.max_chars).to_string()
+    }
+}
+
+/// The sentences of a text, each keeping its final punctuation
+fn sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        let end = index + c.len_utf8();
+        if matches!(c, '.' | '!' | '?')
+            && text[end..].chars().next().is_none_or(char::is_whitespace)
+        {
+            sentences.push(text[start..end].trim().to_string());
+            start = end;
+        }
+    }
+    sentences.push(text[start..].trim().to_string());
+    sentences.retain(|sentence| !sentence.is_empty());
+    sentences
+}
+
+/// At most the last `max_chars` characters of `text`, starting at a word
+fn tail(text: &str, max_chars: usize) -> &str {
+    let count = text.chars().count();
+    if count <= max_chars {
+        return text;
+    }
+    let (cut, _) = text
+        .char_indices()
+        .nth(count - max_chars)
+        .unwrap_or_default();
+    let rest = &text[cut..];
+    // Drop the partial word the cut landed in
+    match rest.find(char::is_whitespace) {
+        Some(space) if !text[..cut].ends_with(char::is_whitespace) => rest[space..].trim_start(),
+        _ => rest.trim_start(),
+    }
+}
+
+/// Assemble the prompt from `whisper_prompt`, vocabulary and recent context
+///
+/// `whisper_prompt` is kept whole. Of the rest of `max_chars`, vocabulary
+/// terms come first, dropped from the end of the list when they don't all
+/// fit, and the context gets what is left, cut at the front. The context
+/// goes last, as Whisper continues from the end of its prompt.
+pub fn build(base: &str, vocabulary: &[String], context: &str, max_chars: usize) -> String {
+    let base = base.trim();
+    let mut remaining = max_chars;
+    if !base.is_empty() {
+        remaining = remaining.saturating_sub(base.chars().count() + 1);
+    }
+
+    let mut terms: Vec<&str> = Vec::new();
+    for term in vocabulary.iter().map(|term| term.trim()) {
+        if !term.is_empty() && !terms.iter().any(|seen| seen.eq_ignore_ascii_case(term)) {
+            terms.push(term);
+        }
+    }
+    let mut glossary = String::new();
+    for term in terms {
+        let candidate = if glossary.is_empty() {
+            format!("Glossary: {}.", term)
+        } else {
+            format!("{}, {}.", glossary.trim_end_matches('.'), term)
+        };
+        if candidate.chars().count() > remaining {
+            break;
+        }
+        glossary = candidate;
+    }
+    if !glossary.is_empty() {
+        remaining = remaining.saturating_sub(glossary.chars().count() + 1);
+    }
+
+    let parts: Vec<&str> = [base, &glossary, tail(context.trim(), remaining)]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect();
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_context() {
+        let mut context = RecentContext::new(3, 60);
+        context.push("Deploy it to Kubernetes. Then ping Dana!");
+        context.push("version 1.2 is out? yes");
+        assert_eq!(context.prompt(), "Then ping Dana! version 1.2 is out? yes");
+
+        context.push("The staging cluster runs on GKE in europe-west.");
+        // Cut to 60 characters at a word boundary
+        assert_eq!(
+            context.prompt(),
+            "is out? yes The staging cluster runs on GKE in europe-west."
+        );
+
+        context.clear();
+        assert_eq!(context.prompt(), "");
+    }
+
+    #[test]
+    fn test_build_prompt() {
+        let vocabulary = vec![
+            "Kubernetes".to_string(),
+            "JSON".to_string(),
+            "kubernetes".to_string(),
+            "PostgreSQL".to_string(),
+        ];
+        assert_eq!(
+            build("Use British spelling.", &vocabulary, "Hi there.", 200),
+            "Use British spelling. Glossary: Kubernetes, JSON, PostgreSQL. Hi there."
+        );
+        // Terms that don't fit are dropped, then the context is cut
+        assert_eq!(
+            build("", &vocabulary, "Ship it now.", 36),
+            "Glossary: Kubernetes, JSON. it now."
+        );
+        assert_eq!(build(" ", &[], "", 100), "");
+    }
+}
@@ -0,0 +1,144 @@
+use crate::history;
+use crate::text_injection::{KeyCombo, Segment};
+use anyhow::{Context, Result};
+use ashpd::desktop::PersistMode;
+use ashpd::desktop::Session;
+use ashpd::desktop::remote_desktop::{DeviceType, KeyState, RemoteDesktop};
+use std::path::PathBuf;
+
+/// X11 keysyms for characters that have dedicated keys
+const RETURN_KEYSYM: i32 = 0xff0d;
+const TAB_KEYSYM: i32 = 0xff09;
+
+/// Offset of the Unicode keysym range (`0x01000000 + code point`)
+const UNICODE_KEYSYM_OFFSET: i32 = 0x0100_0000;
+
+/// Keyboard access through the RemoteDesktop portal
+///
+/// Used by `paste_mode: "portal"` so that injection needs neither `wl-copy`
+/// nor `ydotool`, which aren't reachable from a Flatpak sandbox. Text is typed
+/// as keysyms rather than pasted, and key actions are sent as evdev keycodes.
+///
+/// The desktop asks for permission when the first session starts; the
+/// restore token it returns is saved so later sessions start silently.
+pub struct PortalKeyboard {
+    proxy: RemoteDesktop<'static>,
+    session: Session<'static, RemoteDesktop<'static>>,
+}
+
+impl PortalKeyboard {
+    pub async fn connect() -> Result<Self> {
+        let proxy = RemoteDesktop::new()
+            .await
+            .context("Failed to connect to RemoteDesktop portal")?;
+
+        let session = proxy
+            .create_session()
+            .await
+            .context("Failed to create RemoteDesktop session")?;
+
+        let token_path = token_path()?;
+        let restore_token = std::fs::read_to_string(&token_path).ok();
+
+        proxy
+            .select_devices(
+                &session,
+                DeviceType::Keyboard.into(),
+                restore_token.as_deref().map(str::trim),
+                PersistMode::ExplicitlyRevoked,
+            )
+            .await
+            .context("Failed to select RemoteDesktop devices")?;
+
+        let selected = proxy
+            .start(&session, None)
+            .await
+            .context("Failed to start RemoteDesktop session")?
+            .response()
+            .context("Keyboard access was denied")?;
+
+        if let Some(token) = selected.restore_token() {
+            if let Some(parent) = token_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create state directory: {:?}", parent))?;
+            }
+            if let Err(e) = std::fs::write(&token_path, token) {
+                tracing::warn!("Failed to save RemoteDesktop restore token: {}", e);
+            }
+        }
+
+        tracing::info!("RemoteDesktop portal keyboard session started");
+        Ok(Self { proxy, session })
+    }
+
+    /// Type text segments and press key actions in order
+    pub async fn inject_segments(&self, segments: Vec<Segment>) -> Result<()> {
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => self.type_text(&text).await?,
+                Segment::Keys(combo) => self.press_keys(&combo).await?,
+            }
+        }
+        Ok(())
+    }
+
+    async fn type_text(&self, text: &str) -> Result<()> {
+        tracing::info!(
+            "Typing {} chars via RemoteDesktop portal",
+            text.chars().count()
+        );
+        for c in text.chars() {
+            let keysym = keysym(c);
+            for state in [KeyState::Pressed, KeyState::Released] {
+                self.proxy
+                    .notify_keyboard_keysym(&self.session, keysym, state)
+                    .await
+                    .context("Failed to send key event to RemoteDesktop portal")?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn press_keys(&self, combo: &KeyCombo) -> Result<()> {
+        tracing::info!("Pressing {} via RemoteDesktop portal", combo);
+        for (code, pressed) in combo.key_events() {
+            let state = if pressed {
+                KeyState::Pressed
+            } else {
+                KeyState::Released
+            };
+            self.proxy
+                .notify_keyboard_keycode(&self.session, code as i32, state)
+                .await
+                .context("Failed to send key event to RemoteDesktop portal")?;
+        }
+        Ok(())
+    }
+}
+
+/// Keysym for a character: Latin-1 maps directly, the rest of Unicode is offset
+fn keysym(c: char) -> i32 {
+    match c {
+        '\n' => RETURN_KEYSYM,
+        '\t' => TAB_KEYSYM,
+        ' '..='~' | '\u{a0}'..='\u{ff}' => c as i32,
+        c => UNICODE_KEYSYM_OFFSET + c as i32,
+    }
+}
+
+fn token_path() -> Result<PathBuf> {
+    Ok(history::state_dir()?.join("remote-desktop-token"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keysym() {
+        assert_eq!(keysym('a'), 0x61);
+        assert_eq!(keysym('é'), 0xe9);
+        assert_eq!(keysym('\n'), RETURN_KEYSYM);
+        assert_eq!(keysym('€'), 0x0100_20ac);
+    }
+}
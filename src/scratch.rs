@@ -0,0 +1,110 @@
+use crate::text_processing::SENTENCE_END;
+use regex::Regex;
+
+/// Where a sentence the scratch command deletes back to ends
+const SENTENCE_ENDS: &[char] = &['.', '!', '?', '\n', SENTENCE_END];
+
+/// The "scratch that" command (`scratch_phrase`)
+pub struct ScratchCommand {
+    re: Regex,
+}
+
+impl ScratchCommand {
+    /// The command for `phrase`, or `None` if it is blank
+    pub fn new(phrase: &str) -> Option<Self> {
+        let words: Vec<String> = phrase.split_whitespace().map(regex::escape).collect();
+        if words.is_empty() {
+            return None;
+        }
+        // With the punctuation transcribed or said after it
+        let pattern = format!(r"(?i)\b{}\b[.!?,;:]*{}?", words.join(r"\s+"), SENTENCE_END);
+        let re = Regex::new(&pattern).expect("scratch phrase is escaped");
+        Some(Self { re })
+    }
+
+    /// Delete the sentence before each scratch command, with the command
+    ///
+    /// A sentence ends at `.`, `!`, `?` or a new line. A command with nothing
+    /// before it is kept, for [`Self::strip_leading`] to undo the previous
+    /// dictation.
+    pub fn apply(&self, text: &str) -> String {
+        if !self.re.is_match(text) {
+            return text.to_string();
+        }
+        let mut kept = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(found) = self.re.find(rest) {
+            kept.push_str(&rest[..found.start()]);
+            if kept.trim().is_empty() {
+                kept.push_str(found.as_str());
+            } else {
+                let sentence = kept.trim_end_matches(|c: char| {
+                    c.is_whitespace() || SENTENCE_ENDS.contains(&c) || ",;:".contains(c)
+                });
+                let end = sentence
+                    .char_indices()
+                    .rfind(|(_, c)| SENTENCE_ENDS.contains(c))
+                    .map_or(0, |(index, c)| index + c.len_utf8());
+                kept.truncate(end);
+            }
+            rest = &rest[found.end()..];
+            if kept.is_empty() || kept.ends_with('\n') {
+                rest = rest.trim_start_matches(' ');
+            }
+        }
+        kept.push_str(rest);
+        kept
+    }
+
+    /// The rest of `text` if it starts with the scratch command
+    pub fn strip_leading<'a>(&self, text: &'a str) -> Option<&'a str> {
+        let found = self.re.find(text)?;
+        text[..found.start()]
+            .trim()
+            .is_empty()
+            .then(|| text[found.end()..].trim_start())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_command() {
+        let scratch = ScratchCommand::new("scratch that").unwrap();
+
+        assert_eq!(
+            scratch.apply("Dear Ana. I'm running late. Scratch that. See you soon."),
+            "Dear Ana. See you soon."
+        );
+        assert_eq!(
+            scratch.apply("Meet on Tuesday scratch that Wednesday"),
+            "Wednesday"
+        );
+        assert_eq!(
+            scratch.apply("Line one\nline two scratch that line three"),
+            "Line one\nline three"
+        );
+        // Spoken punctuation leaves its sentence end mark
+        assert_eq!(
+            scratch.apply("Hi.\u{E001} oops.\u{E001} scratch that.\u{E001} bye"),
+            "Hi.\u{E001} bye"
+        );
+        assert_eq!(
+            scratch.apply("a scratch on the table"),
+            "a scratch on the table"
+        );
+
+        // Nothing before it: left for the previous dictation
+        assert_eq!(scratch.apply("Scratch that."), "Scratch that.");
+        assert_eq!(scratch.strip_leading("Scratch that."), Some(""));
+        assert_eq!(
+            scratch.strip_leading("scratch that, see you at five"),
+            Some("see you at five")
+        );
+        assert_eq!(scratch.strip_leading("then scratch that"), None);
+
+        assert!(ScratchCommand::new(" ").is_none());
+    }
+}
@@ -1,17 +1,332 @@
 use anyhow::{Context, Result};
 use ashpd::desktop::global_shortcuts::{GlobalShortcuts, NewShortcut};
 use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 const SHORTCUT_ID: &str = "toggle-recording";
-const DEFAULT_TRIGGER: &str = "LOGO+ALT+d";
+const CORRECTION_SHORTCUT_ID: &str = "mark-corrected";
+const CANCEL_SHORTCUT_ID: &str = "cancel-recording";
+pub const DEFAULT_TRIGGER: &str = "LOGO+ALT+d";
+
+/// Modifier names accepted by the XDG shortcuts specification
+const MODIFIERS: &[&str] = &["CTRL", "ALT", "SHIFT", "NUM", "LOGO"];
+
+/// Prefix marking a trigger that only fires when pressed twice in quick succession
+const DOUBLE_TAP_PREFIX: &str = "double:";
+
+/// Longest gap between the two presses of a double tap
+const DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(400);
+
+/// A global shortcut activation, identified by which shortcut fired
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShortcutEvent {
+    /// Start or stop recording, whatever the shortcut mode
+    Toggle,
+    /// The recording shortcut was pressed
+    RecordPressed,
+    /// The recording shortcut was released (used by hold mode)
+    RecordReleased,
+    /// Learn from the user's correction of the last injected text
+    MarkCorrected,
+    /// Discard the current recording or abort processing
+    Cancel,
+    /// Pause the current recording, or resume a paused one
+    Pause,
+    /// Transcribe the last recording again
+    Retry,
+    /// Make the named profile active
+    SwitchProfile(String),
+    /// Switch to the next language in `languages`
+    CycleLanguage,
+    /// Forget the recent dictations `context_prompt` feeds back
+    ClearContext,
+}
+
+/// What an entry in `shortcut_bindings` does, e.g. `"cancel"` or `"profile:spanish"`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ShortcutAction {
+    Toggle,
+    Cancel,
+    Pause,
+    MarkCorrected,
+    Retry,
+    CycleLanguage,
+    ClearContext,
+    Profile(String),
+}
+
+impl ShortcutAction {
+    /// Description shown in the desktop's shortcut settings
+    fn description(&self) -> String {
+        match self {
+            Self::Toggle => "Toggle voice recording".to_string(),
+            Self::Cancel => "Discard the current recording".to_string(),
+            Self::Pause => "Pause or resume dictation".to_string(),
+            Self::MarkCorrected => "Learn word overrides from a corrected dictation".to_string(),
+            Self::Retry => "Transcribe the last recording again".to_string(),
+            Self::CycleLanguage => "Switch to the next dictation language".to_string(),
+            Self::ClearContext => "Forget the recent dictations used as context".to_string(),
+            Self::Profile(name) => format!("Switch to the {} dictation profile", name),
+        }
+    }
+
+    fn event(&self) -> ShortcutEvent {
+        match self {
+            Self::Toggle => ShortcutEvent::Toggle,
+            Self::Cancel => ShortcutEvent::Cancel,
+            Self::Pause => ShortcutEvent::Pause,
+            Self::MarkCorrected => ShortcutEvent::MarkCorrected,
+            Self::Retry => ShortcutEvent::Retry,
+            Self::CycleLanguage => ShortcutEvent::CycleLanguage,
+            Self::ClearContext => ShortcutEvent::ClearContext,
+            Self::Profile(name) => ShortcutEvent::SwitchProfile(name.clone()),
+        }
+    }
+}
+
+impl fmt::Display for ShortcutAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Toggle => write!(f, "toggle"),
+            Self::Cancel => write!(f, "cancel"),
+            Self::Pause => write!(f, "pause"),
+            Self::MarkCorrected => write!(f, "mark_corrected"),
+            Self::Retry => write!(f, "retry"),
+            Self::CycleLanguage => write!(f, "cycle_language"),
+            Self::ClearContext => write!(f, "clear_context"),
+            Self::Profile(name) => write!(f, "profile:{}", name),
+        }
+    }
+}
+
+impl TryFrom<String> for ShortcutAction {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        match value.as_str() {
+            "toggle" => Ok(Self::Toggle),
+            "cancel" => Ok(Self::Cancel),
+            "pause" => Ok(Self::Pause),
+            "mark_corrected" => Ok(Self::MarkCorrected),
+            "retry" => Ok(Self::Retry),
+            "cycle_language" => Ok(Self::CycleLanguage),
+            "clear_context" => Ok(Self::ClearContext),
+            _ => match value.strip_prefix("profile:") {
+                Some(name) if !name.is_empty() => Ok(Self::Profile(name.to_string())),
+                _ => anyhow::bail!(
+                    "Unknown shortcut action {:?} (expected toggle, cancel, pause, mark_corrected, retry, cycle_language, clear_context or profile:<name>)",
+                    value
+                ),
+            },
+        }
+    }
+}
+
+impl From<ShortcutAction> for String {
+    fn from(action: ShortcutAction) -> Self {
+        action.to_string()
+    }
+}
+
+/// An extra global shortcut and the action it performs
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ShortcutBinding {
+    pub trigger: Shortcut,
+    pub action: ShortcutAction,
+}
+
+/// A preferred trigger in XDG shortcut syntax, e.g. `LOGO+ALT+d`
+///
+/// Parsed when the config is loaded so typos in modifier names are reported
+/// up front rather than silently ignored by the portal.
+///
+/// Key matching is done by the desktop, not by dictator: GNOME and KDE match
+/// the modifier set exactly, so `CTRL+ALT+d` does not fire on
+/// `CTRL+ALT+SHIFT+d` and no separate exact-match option is needed.
+///
+/// A `double:` prefix (e.g. `double:F9`) registers the same trigger but only
+/// acts when it is pressed twice within 400 ms; single presses are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Shortcut {
+    double_tap: bool,
+    modifiers: Vec<String>,
+    key: String,
+}
+
+impl Shortcut {
+    /// The trigger in XDG shortcut syntax, as registered with the portal
+    fn trigger(&self) -> String {
+        let mut trigger = String::new();
+        for modifier in &self.modifiers {
+            trigger.push_str(modifier);
+            trigger.push('+');
+        }
+        trigger.push_str(&self.key);
+        trigger
+    }
+
+    /// Whether both shortcuts are pressed with the same keys, ignoring
+    /// modifier order, key case and `double:`
+    pub fn same_keys(&self, other: &Shortcut) -> bool {
+        let mut modifiers = self.modifiers.clone();
+        let mut other_modifiers = other.modifiers.clone();
+        modifiers.sort();
+        other_modifiers.sort();
+        modifiers == other_modifiers && self.key.eq_ignore_ascii_case(&other.key)
+    }
+}
+
+impl Default for Shortcut {
+    fn default() -> Self {
+        parse_shortcut(DEFAULT_TRIGGER).expect("default trigger is valid")
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.double_tap {
+            write!(f, "{}", DOUBLE_TAP_PREFIX)?;
+        }
+        write!(f, "{}", self.trigger())
+    }
+}
+
+impl TryFrom<String> for Shortcut {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        parse_shortcut(&value)
+    }
+}
+
+impl From<Shortcut> for String {
+    fn from(shortcut: Shortcut) -> Self {
+        shortcut.to_string()
+    }
+}
+
+/// Parse a trigger string such as `LOGO+ALT+d` or `double:F9` into modifiers and a key name
+pub fn parse_shortcut(value: &str) -> Result<Shortcut> {
+    let (double_tap, trigger) = match value.trim().strip_prefix(DOUBLE_TAP_PREFIX) {
+        Some(trigger) => (true, trigger),
+        None => (false, value),
+    };
+
+    let mut parts: Vec<&str> = trigger.split('+').map(str::trim).collect();
+    let key = parts.pop().unwrap_or_default();
+    if key.is_empty() {
+        anyhow::bail!(
+            "Invalid shortcut {:?}: missing key after the last '+'",
+            value
+        );
+    }
+
+    let modifiers = parts
+        .into_iter()
+        .map(|part| {
+            let upper = part.to_ascii_uppercase();
+            if MODIFIERS.contains(&upper.as_str()) {
+                Ok(upper)
+            } else {
+                anyhow::bail!(
+                    "Invalid shortcut {:?}: unknown modifier {:?} (expected one of {})",
+                    value,
+                    part,
+                    MODIFIERS.join(", ")
+                )
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Shortcut {
+        double_tap,
+        modifiers,
+        key: key.to_string(),
+    })
+}
+
+/// Describe the configured triggers and the trigger syntax, for `dictator keys`
+///
+/// Dictator never sees raw key events, so instead of capturing key presses
+/// this points at `wev` for finding a key's name.
+pub fn format_key_help(configured: &[(String, &Shortcut)]) -> String {
+    let mut out = String::from("Configured triggers (the desktop may have rebound them):\n");
+    for (name, shortcut) in configured {
+        out.push_str(&format!("  {:<28} {}\n", name, shortcut));
+    }
+
+    out.push_str(&format!(
+        "\nSyntax: [MODIFIER+...]KEY, optionally prefixed with \"{}\" for a double tap\n\
+         \x20 Modifiers: {} (case-insensitive)\n\
+         \x20 Keys: XKB key names, e.g. d, F13, Escape, Return, space, Print, XF86AudioMute\n\
+         \x20 shortcut_bindings actions: toggle, cancel, pause, mark_corrected, retry,\n\
+         \x20   cycle_language, clear_context, profile:<name>\n\
+         \nTo find a key's name, run `wev` and press the key: use the name after \"sym\".",
+        DOUBLE_TAP_PREFIX,
+        MODIFIERS.join(", ")
+    ));
+    out
+}
+
+/// Turns presses of a `double:` shortcut into activations
+#[derive(Default)]
+struct DoubleTap {
+    last_press: Option<Instant>,
+    /// Whether the current press completed a double tap, so its release counts
+    active: bool,
+}
+
+impl DoubleTap {
+    /// Record a press, returning whether it completes a double tap
+    fn press(&mut self, now: Instant) -> bool {
+        let completed = self
+            .last_press
+            .is_some_and(|last| now.duration_since(last) <= DOUBLE_TAP_WINDOW);
+        // A third tap starts a new gesture rather than completing another one
+        self.last_press = if completed { None } else { Some(now) };
+        self.active = completed;
+        completed
+    }
+
+    /// Record a release, returning whether it ends an activation
+    fn release(&mut self) -> bool {
+        std::mem::take(&mut self.active)
+    }
+}
+
+/// A shortcut to register with the portal
+struct Registration {
+    id: String,
+    description: String,
+    trigger: Shortcut,
+    /// Event sent on activation; `None` for the recording shortcut, which
+    /// also reports releases
+    event: Option<ShortcutEvent>,
+}
 
 /// Monitor for the global shortcut via XDG Desktop Portal.
 ///
-/// Registers a "toggle-recording" shortcut with the compositor (KDE/GNOME/etc)
-/// and sends `()` on the channel each time it's activated.
-/// The user can reconfigure the binding through their desktop's shortcut settings.
-pub async fn monitor_shortcut(tx: mpsc::Sender<()>) -> Result<()> {
+/// Registers a "toggle-recording" shortcut with the compositor (KDE/GNOME/etc),
+/// plus "mark-corrected" and "cancel-recording" shortcuts for the optional
+/// triggers that are set and one "action:<action>" shortcut per binding, and
+/// sends the matching `ShortcutEvent` on the channel each time one is activated.
+/// `double:` triggers are filtered here so only completed double taps (and
+/// their releases) are sent.
+/// Triggers are only a preference: the user can reconfigure the bindings
+/// through their desktop's shortcut settings.
+pub async fn monitor_shortcut(
+    trigger: Shortcut,
+    correction_trigger: Option<Shortcut>,
+    cancel_trigger: Option<Shortcut>,
+    bindings: Vec<ShortcutBinding>,
+    tx: mpsc::Sender<ShortcutEvent>,
+) -> Result<()> {
     let shortcuts = GlobalShortcuts::new()
         .await
         .context("Failed to connect to GlobalShortcuts portal")?;
@@ -21,34 +336,144 @@ pub async fn monitor_shortcut(tx: mpsc::Sender<()>) -> Result<()> {
         .await
         .context("Failed to create GlobalShortcuts session")?;
 
-    let shortcut = NewShortcut::new(SHORTCUT_ID, "Toggle voice recording")
-        .preferred_trigger(Some(DEFAULT_TRIGGER));
+    let mut registrations = vec![Registration {
+        id: SHORTCUT_ID.to_string(),
+        description: "Toggle voice recording".to_string(),
+        trigger,
+        event: None,
+    }];
+    if let Some(trigger) = correction_trigger {
+        registrations.push(Registration {
+            id: CORRECTION_SHORTCUT_ID.to_string(),
+            description: "Learn word overrides from a corrected dictation".to_string(),
+            trigger,
+            event: Some(ShortcutEvent::MarkCorrected),
+        });
+    }
+    if let Some(trigger) = cancel_trigger {
+        registrations.push(Registration {
+            id: CANCEL_SHORTCUT_ID.to_string(),
+            description: "Discard the current recording".to_string(),
+            trigger,
+            event: Some(ShortcutEvent::Cancel),
+        });
+    }
+    for binding in bindings {
+        registrations.push(Registration {
+            id: format!("action:{}", binding.action),
+            description: binding.action.description(),
+            trigger: binding.trigger,
+            event: Some(binding.action.event()),
+        });
+    }
+
+    let triggers: Vec<String> = registrations.iter().map(|r| r.trigger.trigger()).collect();
+    let new_shortcuts: Vec<NewShortcut> = registrations
+        .iter()
+        .zip(&triggers)
+        .map(|(registration, trigger)| {
+            NewShortcut::new(registration.id.as_str(), registration.description.as_str())
+                .preferred_trigger(Some(trigger.as_str()))
+        })
+        .collect();
 
     shortcuts
-        .bind_shortcuts(&session, &[shortcut], None)
+        .bind_shortcuts(&session, &new_shortcuts, None)
         .await
         .context("Failed to bind shortcuts")?
         .response()
         .context("Shortcut binding was rejected")?;
 
     tracing::info!(
-        "Global shortcut registered (default: {}). Reconfigure in System Settings > Shortcuts.",
-        DEFAULT_TRIGGER
+        "Global shortcut registered (preferred: {}). Reconfigure in System Settings > Shortcuts.",
+        registrations[0].trigger
     );
+    for registration in &registrations[1..] {
+        tracing::info!(
+            "Shortcut {:?} registered (preferred: {})",
+            registration.description,
+            registration.trigger
+        );
+    }
 
-    let mut stream = shortcuts
+    let mut double_taps: HashMap<String, DoubleTap> = registrations
+        .iter()
+        .filter(|r| r.trigger.double_tap)
+        .map(|r| (r.id.clone(), DoubleTap::default()))
+        .collect();
+    let events: HashMap<String, Option<ShortcutEvent>> =
+        registrations.into_iter().map(|r| (r.id, r.event)).collect();
+
+    let activated = shortcuts
         .receive_activated()
         .await
-        .context("Failed to listen for shortcut activations")?;
+        .context("Failed to listen for shortcut activations")?
+        .map(|activated| (activated.shortcut_id().to_string(), true));
+    let deactivated = shortcuts
+        .receive_deactivated()
+        .await
+        .context("Failed to listen for shortcut releases")?
+        .map(|deactivated| (deactivated.shortcut_id().to_string(), false));
+    let mut stream = futures::stream::select(activated, deactivated);
 
-    while let Some(activated) = stream.next().await {
-        if activated.shortcut_id() == SHORTCUT_ID {
-            tracing::debug!("Shortcut activated: {}", SHORTCUT_ID);
-            if tx.send(()).await.is_err() {
-                break;
+    while let Some((id, pressed)) = stream.next().await {
+        if let Some(double_tap) = double_taps.get_mut(&id) {
+            let counts = if pressed {
+                double_tap.press(Instant::now())
+            } else {
+                double_tap.release()
+            };
+            if !counts {
+                continue;
             }
         }
+
+        let event = match (events.get(&id), pressed) {
+            (Some(None), true) => ShortcutEvent::RecordPressed,
+            (Some(None), false) => ShortcutEvent::RecordReleased,
+            (Some(Some(event)), true) => event.clone(),
+            _ => continue,
+        };
+        tracing::debug!(
+            "Shortcut {}: {}",
+            if pressed { "activated" } else { "released" },
+            id
+        );
+        if tx.send(event).await.is_err() {
+            break;
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_key_help() {
+        let shortcut = parse_shortcut("double:F13").unwrap();
+        let help = format_key_help(&[("shortcut".to_string(), &shortcut)]);
+        assert!(help.contains("shortcut                     double:F13\n"));
+        assert!(help.contains("Modifiers: CTRL, ALT, SHIFT, NUM, LOGO"));
+    }
+
+    #[test]
+    fn test_double_tap() {
+        let shortcut = parse_shortcut("double:CTRL+F9").unwrap();
+        assert!(shortcut.double_tap);
+        assert_eq!(shortcut.trigger(), "CTRL+F9");
+        assert_eq!(shortcut.to_string(), "double:CTRL+F9");
+
+        let start = Instant::now();
+        let mut gesture = DoubleTap::default();
+        assert!(!gesture.press(start));
+        assert!(!gesture.release());
+        assert!(gesture.press(start + Duration::from_millis(300)));
+        assert!(gesture.release());
+        // Too slow: the second press starts a new gesture instead
+        assert!(!gesture.press(start + Duration::from_secs(1)));
+        assert!(!gesture.press(start + Duration::from_secs(2)));
+    }
+}
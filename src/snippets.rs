@@ -0,0 +1,145 @@
+use crate::text_processing;
+use anyhow::Result;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, TimeZone};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+use std::sync::LazyLock;
+
+/// Stands in for a snippet while the rest of the dictation is processed, so
+/// punctuation commands and capitalization don't touch its text
+const START: char = '\u{E002}';
+const END: char = '\u{E003}';
+
+static MARKER: LazyLock<Regex> = LazyLock::new(|| Regex::new("\u{E002}([0-9]+)\u{E003}").unwrap());
+
+/// `{date}`, `{time}` and `{date:<strftime format>}`
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{(date|time)(?::([^}]*))?\}").unwrap());
+
+/// The "insert <name>" command of each of the `snippets`, with its template
+pub fn commands(snippets: &HashMap<String, String>) -> Vec<(Regex, String)> {
+    let phrases: HashMap<String, String> = snippets
+        .iter()
+        .map(|(name, template)| (format!("insert {}", name.trim()), template.clone()))
+        .collect();
+    text_processing::phrase_commands(&phrases)
+}
+
+/// Replace "insert <name>" commands with markers for [`expand`]
+pub fn mark(text: &str, commands: &[(Regex, String)]) -> String {
+    let mut result = text.to_string();
+    for (index, (re, _)) in commands.iter().enumerate() {
+        let marker = format!("{}{}{}", START, index, END);
+        result = re
+            .replace_all(&result, regex::NoExpand(&marker))
+            .to_string();
+    }
+    result
+}
+
+/// Whether the text ends with a snippet, which brings its own punctuation
+pub fn ends_with_snippet(text: &str) -> bool {
+    text.ends_with(END)
+}
+
+/// Replace the markers left by [`mark`] with their snippets, filling in the
+/// date and time in the local timezone
+pub fn expand(text: &str, commands: &[(Regex, String)]) -> String {
+    if !text.contains(START) {
+        return text.to_string();
+    }
+    let now = Local::now();
+    MARKER
+        .replace_all(text, |captures: &Captures| {
+            captures[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| commands.get(index))
+                .map(|(_, template)| render(template, &now))
+                .unwrap_or_default()
+        })
+        .to_string()
+}
+
+/// The strftime format of a placeholder: its own, or `%Y-%m-%d` for
+/// `{date}` and `%H:%M` for `{time}`
+fn placeholder_format<'a>(captures: &Captures<'a>) -> &'a str {
+    match (captures.get(1).map(|kind| kind.as_str()), captures.get(2)) {
+        (_, Some(format)) => format.as_str(),
+        (Some("date"), None) => "%Y-%m-%d",
+        _ => "%H:%M",
+    }
+}
+
+/// Fill in a snippet's placeholders with the time `now`
+fn render<Tz: TimeZone>(template: &str, now: &DateTime<Tz>) -> String
+where
+    Tz::Offset: Display,
+{
+    PLACEHOLDER
+        .replace_all(template, |captures: &Captures| {
+            let mut formatted = String::new();
+            match write!(formatted, "{}", now.format(placeholder_format(captures))) {
+                Ok(()) => formatted,
+                // Rejected by `validate`, so not expected here
+                Err(_) => captures[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// Check the strftime formats of a snippet's placeholders
+pub fn validate(template: &str) -> Result<()> {
+    for captures in PLACEHOLDER.captures_iter(template) {
+        let format = placeholder_format(&captures);
+        if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+            return Err(anyhow::anyhow!(
+                "Invalid strftime format {:?} in {}",
+                format,
+                &captures[0]
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippets() {
+        let snippets: HashMap<String, String> = [
+            ("signature", "Best regards,\nDana"),
+            ("meeting header", "Meeting notes, {date:%A %d %B}\n{time}: "),
+        ]
+        .into_iter()
+        .map(|(name, template)| (name.to_string(), template.to_string()))
+        .collect();
+        let commands = commands(&snippets);
+
+        let marked = mark("thanks period Insert signature", &commands);
+        assert!(ends_with_snippet(&marked));
+        // No placeholders to fill in
+        assert_eq!(
+            expand(&marked, &commands),
+            "thanks period Best regards,\nDana"
+        );
+        assert_eq!(mark("insert nothing", &commands), "insert nothing");
+
+        let now = chrono::FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2026, 10, 16, 14, 23, 0)
+            .unwrap();
+        assert_eq!(
+            render("Meeting notes, {date:%A %d %B}\n{time}: ", &now),
+            "Meeting notes, Friday 16 October\n14:23: "
+        );
+        assert_eq!(render("Due {date}", &now), "Due 2026-10-16");
+
+        assert!(validate("Meeting notes, {date:%A %d %B}\n{time}: ").is_ok());
+        assert!(validate("Due {date:%d %Q}").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::tokenizer::{self, Rewriter, Token, says};
+
+/// NATO alphabet code words
+const NATO: &[(&str, char)] = &[
+    ("alpha", 'a'),
+    ("alfa", 'a'),
+    ("bravo", 'b'),
+    ("charlie", 'c'),
+    ("delta", 'd'),
+    ("echo", 'e'),
+    ("foxtrot", 'f'),
+    ("golf", 'g'),
+    ("hotel", 'h'),
+    ("india", 'i'),
+    ("juliet", 'j'),
+    ("juliett", 'j'),
+    ("kilo", 'k'),
+    ("lima", 'l'),
+    ("mike", 'm'),
+    ("november", 'n'),
+    ("oscar", 'o'),
+    ("papa", 'p'),
+    ("quebec", 'q'),
+    ("romeo", 'r'),
+    ("sierra", 's'),
+    ("tango", 't'),
+    ("uniform", 'u'),
+    ("victor", 'v'),
+    ("whiskey", 'w'),
+    ("whisky", 'w'),
+    ("x-ray", 'x'),
+    ("xray", 'x'),
+    ("yankee", 'y'),
+    ("zulu", 'z'),
+];
+
+const DIGITS: &[&str] = &[
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+
+/// Separators common in identifiers and ticket IDs
+const SYMBOLS: &[(&str, char)] = &[
+    ("dash", '-'),
+    ("hyphen", '-'),
+    ("underscore", '_'),
+    ("dot", '.'),
+    ("slash", '/'),
+];
+
+/// Words that make the next letter uppercase
+const CAPITAL: &[&str] = &["capital", "uppercase"];
+
+const SPELL: &[&str] = &["spell"];
+const START: &[&str] = &["start", "spelling"];
+const STOP: &[&[&str]] = &[&["stop", "spelling"], &["end", "spelling"]];
+
+/// What a spelled word stands for: a NATO code word, a single letter, a
+/// digit or number, or an identifier separator
+fn spelled(token: &Token) -> Option<String> {
+    let word = token.bare.as_str();
+    if let Some((_, letter)) = NATO.iter().find(|(name, _)| *name == word) {
+        return Some(letter.to_string());
+    }
+    if let Some(digit) = DIGITS.iter().position(|name| *name == word) {
+        return Some(digit.to_string());
+    }
+    if let Some((_, symbol)) = SYMBOLS.iter().find(|(name, _)| *name == word) {
+        return Some(symbol.to_string());
+    }
+    let mut chars = word.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) if letter.is_alphabetic() => Some(letter.to_string()),
+        _ if !word.is_empty() && word.chars().all(|c| c.is_ascii_digit()) => Some(word.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether the words after "spell" are spelling rather than speech: a NATO
+/// code word, or at least two letters, digits or separators
+fn spells_something(spelled_words: &[Token]) -> bool {
+    let is_nato = |token: &Token| NATO.iter().any(|(name, _)| *name == token.bare);
+    spelled_words.iter().any(is_nato)
+        || spelled_words
+            .iter()
+            .filter(|token| spelled(token).is_some())
+            .count()
+            >= 2
+}
+
+/// Spell the words from `index` into `out`, returning where spelling stopped
+///
+/// With `until`, every word up to that phrase is spelled, unknown words
+/// kept as said; otherwise spelling stops at the first unknown word.
+fn spell(tokens: &[Token], mut index: usize, until: Option<&[&[&str]]>, out: &mut String) -> usize {
+    let mut capital = false;
+    while index < tokens.len() {
+        if until.is_some_and(|stop| stop.iter().any(|phrase| says(tokens, index, phrase))) {
+            break;
+        }
+        let token = &tokens[index];
+        if CAPITAL.contains(&token.bare.as_str()) {
+            capital = true;
+            index += 1;
+            continue;
+        }
+        let text = match spelled(token) {
+            Some(text) => text,
+            None if until.is_some() => token.bare.clone(),
+            None => break,
+        };
+        if capital {
+            out.extend(text.chars().flat_map(char::to_uppercase));
+        } else {
+            out.push_str(&text);
+        }
+        capital = false;
+        index += 1;
+    }
+    index
+}
+
+/// Turn spelled-out words into the string they spell
+///
+/// "spell alpha bravo seven" becomes `ab7`, spelling up to the first word
+/// that isn't a letter, digit or separator. It takes a NATO word or two
+/// spelled words, so "spell a name" and "spell one thing" are left alone. "start spelling" spells every
+/// word until "stop spelling" (or the end of the dictation), for longer
+/// strings. "capital" makes the next letter uppercase.
+pub fn apply(text: &str) -> String {
+    let tokens = tokenizer::tokenize(text);
+    let mentioned =
+        (0..tokens.len()).any(|index| says(&tokens, index, SPELL) || says(&tokens, index, START));
+    if !mentioned {
+        return text.to_string();
+    }
+
+    let mut out = Rewriter::new(text);
+    let mut index = 0;
+    while index < tokens.len() {
+        let (start, until) = if says(&tokens, index, START) {
+            (index + START.len(), Some(STOP))
+        } else if says(&tokens, index, SPELL) {
+            (index + SPELL.len(), None)
+        } else {
+            out.keep(&tokens[index..=index]);
+            index += 1;
+            continue;
+        };
+
+        let mut word = String::new();
+        let end = spell(&tokens, start, until, &mut word);
+        if until.is_none() && !spells_something(&tokens[start..end]) {
+            // "how do you spell it", "spell a name": not a command
+            out.keep(&tokens[index..=index]);
+            index += 1;
+            continue;
+        }
+
+        let mut next = end;
+        if let Some(stop) = STOP.iter().find(|phrase| says(&tokens, end, phrase)) {
+            next = end + stop.len();
+        }
+        if next > start {
+            word.push_str(tokens[next - 1].trailing_punctuation());
+        }
+        out.replace(&tokens[index..next], &word);
+        index = next;
+    }
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spelling() {
+        assert_eq!(apply("spell alpha bravo seven"), "ab7");
+        assert_eq!(
+            apply("The ticket is spell Kilo, dash, four, two. Thanks"),
+            "The ticket is k-42. Thanks"
+        );
+        assert_eq!(
+            apply("user spell capital juliet D O E 99 please"),
+            "user Jdoe99 please"
+        );
+        assert_eq!(apply("how do you spell it"), "how do you spell it");
+        assert_eq!(
+            apply("can you spell a name for me"),
+            "can you spell a name for me"
+        );
+        assert_eq!(apply("please spell one thing"), "please spell one thing");
+        assert_eq!(apply("spell x-ray"), "x");
+
+        // Spelling mode spells every word until it's stopped
+        assert_eq!(
+            apply("start spelling Papa x-ray 2 dot ok stop spelling is the host"),
+            "px2.ok is the host"
+        );
+        assert_eq!(apply("start spelling sierra one"), "s1");
+
+        // Text around the spelled word keeps its new lines and spacing
+        assert_eq!(
+            apply("Host:\n  spell alpha one\n\nthanks"),
+            "Host:\n  a1\n\nthanks"
+        );
+    }
+}
@@ -1,4 +1,8 @@
 use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
 use std::io::Write;
 use std::process::{Command, Stdio};
 use std::time::Duration;
@@ -6,6 +10,411 @@ use tokio::task;
 
 use crate::config::PasteMode;
 
+/// Delay between pasting text and pressing a key action, so the target app has
+/// fetched the clipboard contents before e.g. Enter sends the message
+const KEY_ACTION_DELAY: Duration = Duration::from_millis(150);
+
+/// Modifier names and their Linux input event codes
+const MODIFIER_CODES: &[(&str, u16)] = &[
+    ("ctrl", 29),
+    ("control", 29),
+    ("shift", 42),
+    ("alt", 56),
+    ("super", 125),
+    ("logo", 125),
+    ("meta", 125),
+];
+
+/// Named (non-letter, non-digit) keys and their Linux input event codes
+const KEY_CODES: &[(&str, u16)] = &[
+    ("escape", 1),
+    ("esc", 1),
+    ("minus", 12),
+    ("equal", 13),
+    ("backspace", 14),
+    ("tab", 15),
+    ("return", 28),
+    ("enter", 28),
+    ("semicolon", 39),
+    ("apostrophe", 40),
+    ("grave", 41),
+    ("backslash", 43),
+    ("comma", 51),
+    ("period", 52),
+    ("dot", 52),
+    ("slash", 53),
+    ("space", 57),
+    ("f1", 59),
+    ("f2", 60),
+    ("f3", 61),
+    ("f4", 62),
+    ("f5", 63),
+    ("f6", 64),
+    ("f7", 65),
+    ("f8", 66),
+    ("f9", 67),
+    ("f10", 68),
+    ("f11", 87),
+    ("f12", 88),
+    ("home", 102),
+    ("up", 103),
+    ("pageup", 104),
+    ("left", 105),
+    ("right", 106),
+    ("end", 107),
+    ("down", 108),
+    ("pagedown", 109),
+    ("insert", 110),
+    ("delete", 111),
+];
+
+/// Letters in Linux input event code order, per keyboard row
+const LETTER_ROWS: &[(&str, u16)] = &[("qwertyuiop", 16), ("asdfghjkl", 30), ("zxcvbnm", 44)];
+
+fn key_code(name: &str) -> Option<u16> {
+    let lower = name.to_ascii_lowercase();
+    if let Some((_, code)) = KEY_CODES.iter().find(|(key, _)| *key == lower) {
+        return Some(*code);
+    }
+
+    let mut chars = lower.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return None;
+    };
+    match c {
+        '1'..='9' => Some(2 + (c as u16 - '1' as u16)),
+        '0' => Some(11),
+        _ => LETTER_ROWS
+            .iter()
+            .find_map(|(row, first)| row.find(c).map(|offset| first + offset as u16)),
+    }
+}
+
+/// A key press with optional modifiers, e.g. `Return` or `shift+Return`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyCombo {
+    name: String,
+    modifiers: Vec<u16>,
+    key: u16,
+}
+
+impl KeyCombo {
+    pub fn parse(value: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = value.split('+').map(str::trim).collect();
+        let key_name = parts.pop().unwrap_or_default();
+        let key = key_code(key_name).with_context(|| {
+            format!("Invalid key combo {:?}: unknown key {:?}", value, key_name)
+        })?;
+
+        let modifiers = parts
+            .into_iter()
+            .map(|part| {
+                MODIFIER_CODES
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(part))
+                    .map(|(_, code)| *code)
+                    .with_context(|| {
+                        format!("Invalid key combo {:?}: unknown modifier {:?}", value, part)
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            name: value.to_string(),
+            modifiers,
+            key,
+        })
+    }
+
+    /// Key events as (code, pressed): press modifiers, tap the key, release
+    /// modifiers in reverse order
+    pub fn key_events(&self) -> Vec<(u16, bool)> {
+        let mut events: Vec<(u16, bool)> = self.modifiers.iter().map(|&m| (m, true)).collect();
+        events.push((self.key, true));
+        events.push((self.key, false));
+        events.extend(self.modifiers.iter().rev().map(|&m| (m, false)));
+        events
+    }
+
+    /// Key event sequence in `ydotool key` syntax
+    pub fn ydotool_sequence(&self) -> String {
+        self.key_events()
+            .into_iter()
+            .map(|(code, pressed)| format!("{}:{}", code, u8::from(pressed)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl TryFrom<String> for KeyCombo {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<KeyCombo> for String {
+    fn from(combo: KeyCombo) -> Self {
+        combo.name
+    }
+}
+
+/// A piece of a dictation: text to insert, or keys to press
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Keys(KeyCombo),
+}
+
+/// Spoken commands that press keys instead of inserting text ("send it" → Enter)
+pub struct KeyCommands {
+    pattern: Option<Regex>,
+    commands: HashMap<String, KeyCombo>,
+}
+
+impl KeyCommands {
+    pub fn new(commands: &HashMap<String, KeyCombo>) -> Self {
+        let commands: HashMap<String, KeyCombo> = commands
+            .iter()
+            .map(|(phrase, combo)| (normalize_phrase(phrase), combo.clone()))
+            .filter(|(phrase, _)| !phrase.is_empty())
+            .collect();
+
+        // Longest phrases first so "send it now" wins over "send it"
+        let mut phrases: Vec<&String> = commands.keys().collect();
+        phrases.sort_by_key(|phrase| std::cmp::Reverse(phrase.len()));
+        let alternatives: Vec<String> = phrases
+            .iter()
+            .map(|phrase| {
+                phrase
+                    .split(' ')
+                    .map(regex::escape)
+                    .collect::<Vec<_>>()
+                    .join(r"\s+")
+            })
+            .collect();
+
+        // Whisper often attaches punctuation to the command ("send it.")
+        let pattern = (!alternatives.is_empty()).then(|| {
+            Regex::new(&format!(r"(?i)\b(?:{})\b[.!?,]?", alternatives.join("|"))).unwrap()
+        });
+
+        Self { pattern, commands }
+    }
+
+    /// Split a transcript into text and key-press segments
+    pub fn split(&self, text: &str) -> Vec<Segment> {
+        let Some(pattern) = &self.pattern else {
+            return vec![Segment::Text(text.to_string())];
+        };
+
+        let mut segments = Vec::new();
+        let mut last = 0;
+        for m in pattern.find_iter(text) {
+            let Some(combo) = self.commands.get(&normalize_phrase(m.as_str())) else {
+                continue;
+            };
+            if m.start() > last {
+                segments.push(Segment::Text(text[last..m.start()].to_string()));
+            }
+            segments.push(Segment::Keys(combo.clone()));
+            last = m.end();
+        }
+        if last < text.len() {
+            segments.push(Segment::Text(text[last..].to_string()));
+        }
+        segments
+    }
+}
+
+pub fn normalize_phrase(phrase: &str) -> String {
+    phrase
+        .trim_end_matches(['.', '!', '?', ','])
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Inject a sequence of text and key-press segments in order
+///
+/// Key presses need ydotool, so with the clipboard-only modes (`None` and
+/// `Append`) they are dropped and the text segments are copied as one string.
+/// `Append` adds it to the current clipboard contents after `append_separator`.
+pub async fn inject_segments(
+    segments: Vec<Segment>,
+    paste_mode: &PasteMode,
+    append_separator: &str,
+) -> Result<()> {
+    if *paste_mode == PasteMode::Portal {
+        anyhow::bail!("paste_mode \"portal\" injects through the RemoteDesktop portal");
+    }
+
+    if matches!(paste_mode, PasteMode::None | PasteMode::Append) {
+        let text: Vec<String> = segments
+            .into_iter()
+            .filter_map(|segment| match segment {
+                Segment::Text(text) => Some(text),
+                Segment::Keys(combo) => {
+                    tracing::info!(
+                        "Ignoring key action {} (paste_mode: {:?})",
+                        combo,
+                        paste_mode
+                    );
+                    None
+                }
+            })
+            .collect();
+        let text = text.join(" ");
+        if *paste_mode == PasteMode::Append {
+            return append_to_clipboard(text, append_separator).await;
+        }
+        return inject_text(text, paste_mode).await;
+    }
+
+    let mut after_text = false;
+    for segment in segments {
+        match segment {
+            Segment::Text(text) if text.is_empty() => {}
+            Segment::Text(text) => {
+                inject_text(text, paste_mode).await?;
+                after_text = true;
+            }
+            Segment::Keys(combo) => {
+                if after_text {
+                    tokio::time::sleep(KEY_ACTION_DELAY).await;
+                }
+                press_keys(&combo).await?;
+                after_text = false;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Append text to the current clipboard contents
+///
+/// The separator is only inserted when the clipboard already holds text, so the
+/// first dictation after copying something else still starts cleanly.
+async fn append_to_clipboard(text: String, separator: &str) -> Result<()> {
+    if text.is_empty() {
+        return Ok(());
+    }
+
+    let existing = read_clipboard().await?;
+
+    tracing::info!(
+        "Appending {} chars to {} chars on the clipboard",
+        text.chars().count(),
+        existing.chars().count()
+    );
+    inject_text(
+        join_clipboard(&existing, separator, &text),
+        &PasteMode::Append,
+    )
+    .await
+}
+
+/// Current clipboard text via wl-paste, empty if the clipboard holds no text
+pub async fn read_clipboard() -> Result<String> {
+    task::spawn_blocking(|| {
+        // wl-paste fails when the clipboard is empty or holds no text
+        Command::new("wl-paste")
+            .args(["--no-newline", "--type", "text"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default()
+    })
+    .await
+    .context("spawn_blocking failed")
+}
+
+fn join_clipboard(existing: &str, separator: &str, text: &str) -> String {
+    if existing.is_empty() {
+        text.to_string()
+    } else {
+        format!("{existing}{separator}{text}")
+    }
+}
+
+/// Press a key combination via ydotool
+pub async fn press_keys(combo: &KeyCombo) -> Result<()> {
+    tracing::info!("Pressing {}", combo);
+    let sequence = combo.ydotool_sequence();
+
+    task::spawn_blocking(move || {
+        Command::new("ydotool")
+            .args(["key", &sequence])
+            .output()
+            .context("Failed to execute ydotool")
+    })
+    .await
+    .context("spawn_blocking failed")??;
+
+    Ok(())
+}
+
+/// How many BackSpace presses delete `text` once typed
+///
+/// Editors delete a whole emoji per press, so variation selectors, skin tone
+/// modifiers and combining marks don't count, a zero-width joiner joins the
+/// next character to the emoji before it, and a flag is two regional
+/// indicators.
+pub fn typed_len(text: &str) -> usize {
+    let mut count = 0;
+    let mut joined = false;
+    let mut regional_indicators = 0;
+    for c in text.chars() {
+        let attached = matches!(c,
+            '\u{FE00}'..='\u{FE0F}' | '\u{1F3FB}'..='\u{1F3FF}' | '\u{0300}'..='\u{036F}' | '\u{20E3}');
+        if c == '\u{200D}' {
+            joined = true;
+            continue;
+        }
+        if attached || std::mem::take(&mut joined) {
+            continue;
+        }
+        if ('\u{1F1E6}'..='\u{1F1FF}').contains(&c) {
+            regional_indicators += 1;
+            if regional_indicators % 2 == 0 {
+                continue;
+            }
+        } else {
+            regional_indicators = 0;
+        }
+        count += 1;
+    }
+    count
+}
+
+/// Delete the `count` characters before the cursor with BackSpace presses
+pub async fn delete_chars(count: usize) -> Result<()> {
+    tracing::info!("Deleting {} chars", count);
+    let backspace = KeyCombo::parse("backspace")?.ydotool_sequence();
+    let sequence = vec![backspace; count].join(" ");
+
+    task::spawn_blocking(move || {
+        Command::new("ydotool")
+            .args(["key", &sequence])
+            .output()
+            .context("Failed to execute ydotool")
+    })
+    .await
+    .context("spawn_blocking failed")??;
+
+    Ok(())
+}
+
 /// Inject processed text into the system via clipboard and keyboard simulation
 ///
 /// This function:
@@ -13,7 +422,7 @@ use crate::config::PasteMode;
 /// - Waits for clipboard to settle (if paste_mode is not None)
 /// - Triggers paste via ydotool with the specified keyboard shortcut (unless paste_mode is None)
 pub async fn inject_text(processed_text: String, paste_mode: &PasteMode) -> Result<()> {
-    tracing::info!("Processing text: {} chars", processed_text.len());
+    tracing::info!("Processing text: {} chars", processed_text.chars().count());
 
     let paste_mode = *paste_mode;
 
@@ -34,10 +443,10 @@ pub async fn inject_text(processed_text: String, paste_mode: &PasteMode) -> Resu
 
         child.wait().context("wl-copy failed")?;
 
-        // Only trigger paste if not in "none" mode
+        // Only trigger paste if not in a clipboard-only mode
         match paste_mode {
-            PasteMode::None => {
-                tracing::info!("Text copied to clipboard (paste_mode: none)");
+            PasteMode::None | PasteMode::Append | PasteMode::Portal => {
+                tracing::info!("Text copied to clipboard (paste_mode: {:?})", paste_mode);
             }
             _ => {
                 // Wait for clipboard to settle
@@ -48,7 +457,7 @@ pub async fn inject_text(processed_text: String, paste_mode: &PasteMode) -> Resu
                     PasteMode::Super => "125:1 47:1 47:0 125:0",              // Super+V
                     PasteMode::CtrlShift => "29:1 42:1 47:1 47:0 42:0 29:0", // Ctrl+Shift+V
                     PasteMode::Ctrl => "29:1 47:1 47:0 29:0",                // Ctrl+V
-                    PasteMode::None => unreachable!(),
+                    PasteMode::None | PasteMode::Append | PasteMode::Portal => unreachable!(),
                 };
 
                 Command::new("ydotool")
@@ -66,3 +475,65 @@ pub async fn inject_text(processed_text: String, paste_mode: &PasteMode) -> Resu
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_combo_parsing() {
+        assert_eq!(
+            KeyCombo::parse("Return").unwrap().ydotool_sequence(),
+            "28:1 28:0"
+        );
+        assert_eq!(
+            KeyCombo::parse("shift+Return").unwrap().ydotool_sequence(),
+            "42:1 28:1 28:0 42:0"
+        );
+        assert_eq!(
+            KeyCombo::parse("ctrl+shift+v").unwrap().ydotool_sequence(),
+            "29:1 42:1 47:1 47:0 42:0 29:0"
+        );
+        assert!(KeyCombo::parse("hyper+Return").is_err());
+        assert!(KeyCombo::parse("ctrl+").is_err());
+    }
+
+    #[test]
+    fn test_typed_len() {
+        assert_eq!(typed_len("héllo"), 5);
+        assert_eq!(typed_len("ok 👍"), 4);
+        // Variation selector, skin tone, family (joined) and flag
+        assert_eq!(typed_len("❤️👍🏽👨‍👩‍👧🇩🇪"), 4);
+    }
+
+    #[test]
+    fn test_join_clipboard() {
+        assert_eq!(join_clipboard("", "\n", "first"), "first");
+        assert_eq!(join_clipboard("first", "\n", "second"), "first\nsecond");
+    }
+
+    #[test]
+    fn test_key_commands_split() {
+        let mut commands = HashMap::new();
+        commands.insert("send it".to_string(), KeyCombo::parse("Return").unwrap());
+        commands.insert(
+            "new line".to_string(),
+            KeyCombo::parse("shift+Return").unwrap(),
+        );
+        let commands = KeyCommands::new(&commands);
+
+        assert_eq!(
+            commands.split("Hello there. New line how are you? Send it."),
+            vec![
+                Segment::Text("Hello there. ".to_string()),
+                Segment::Keys(KeyCombo::parse("shift+Return").unwrap()),
+                Segment::Text(" how are you? ".to_string()),
+                Segment::Keys(KeyCombo::parse("Return").unwrap()),
+            ]
+        );
+        assert_eq!(
+            commands.split("nothing to do"),
+            vec![Segment::Text("nothing to do".to_string())]
+        );
+    }
+}
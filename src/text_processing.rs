@@ -1,18 +1,224 @@
+use crate::config::{RegexRule, SymbolStage};
+use crate::scratch::ScratchCommand;
+use crate::{case_commands, emoji, numbers, snippets, spelling};
 use regex::Regex;
 use std::collections::HashMap;
 
+/// English punctuation commands, applied in order
+///
+/// Order matters when one command contains another as a separate word, which is
+/// why the localized tables list e.g. "punto y coma" before "punto".
+const ENGLISH_PUNCTUATION: &[(&str, &str)] = &[
+    ("period", "."),
+    ("comma", ","),
+    ("question mark", "?"),
+    ("exclamation mark", "!"),
+    ("colon", ":"),
+    ("semicolon", ";"),
+    ("new line", "\n"),
+    ("tab", "\t"),
+    ("dash", "-"),
+    ("underscore", "_"),
+    ("open paren", "("),
+    ("close paren", ")"),
+    ("open bracket", "["),
+    ("close bracket", "]"),
+    ("open brace", "{"),
+    ("close brace", "}"),
+    ("at symbol", "@"),
+    ("hash", "#"),
+    ("plus", "+"),
+    ("equals", "="),
+    ("asterisk", "*"),
+    ("ampersand", "&"),
+    ("percent", "%"),
+    ("dollar sign", "$"),
+    ("backslash", "\\"),
+    ("slash", "/"),
+    ("pipe", "|"),
+    ("caret", "^"),
+    ("tilde", "~"),
+    ("backtick", "`"),
+    ("quote", "\""),
+    ("single quote", "'"),
+    ("less than", "<"),
+    ("greater than", ">"),
+];
+
+const SPANISH_PUNCTUATION: &[(&str, &str)] = &[
+    ("punto y coma", ";"),
+    ("dos puntos", ":"),
+    ("punto", "."),
+    ("coma", ","),
+    ("signo de interrogación", "?"),
+    ("signo de exclamación", "!"),
+    ("nueva línea", "\n"),
+    ("abrir paréntesis", "("),
+    ("cerrar paréntesis", ")"),
+    ("guion bajo", "_"),
+    ("guion", "-"),
+    ("comillas", "\""),
+    ("arroba", "@"),
+];
+
+const GERMAN_PUNCTUATION: &[(&str, &str)] = &[
+    ("punkt", "."),
+    ("komma", ","),
+    ("fragezeichen", "?"),
+    ("ausrufezeichen", "!"),
+    ("doppelpunkt", ":"),
+    ("semikolon", ";"),
+    ("neue zeile", "\n"),
+    ("klammer auf", "("),
+    ("klammer zu", ")"),
+    ("bindestrich", "-"),
+    ("unterstrich", "_"),
+    ("anführungszeichen", "\""),
+    ("at-zeichen", "@"),
+];
+
+const FRENCH_PUNCTUATION: &[(&str, &str)] = &[
+    ("point d'interrogation", "?"),
+    ("point d'exclamation", "!"),
+    ("point-virgule", ";"),
+    ("deux-points", ":"),
+    ("point", "."),
+    ("virgule", ","),
+    ("nouvelle ligne", "\n"),
+    ("ouvrir la parenthèse", "("),
+    ("fermer la parenthèse", ")"),
+    ("tiret", "-"),
+    ("guillemets", "\""),
+    ("arobase", "@"),
+];
+
+/// Build the punctuation commands for a language code, falling back to English
+///
+/// English commands are case-sensitive as they always have been; localized
+/// commands are matched case-insensitively since Whisper often capitalizes them
+/// (e.g. German nouns).
+fn punctuation_commands(language: Option<&str>) -> Vec<PunctuationCommand> {
+    let (table, case_insensitive) = match language.map(|l| l.to_ascii_lowercase()).as_deref() {
+        Some("es") => (SPANISH_PUNCTUATION, true),
+        Some("de") => (GERMAN_PUNCTUATION, true),
+        Some("fr") => (FRENCH_PUNCTUATION, true),
+        _ => (ENGLISH_PUNCTUATION, false),
+    };
+
+    table
+        .iter()
+        .map(|(phrase, replacement)| PunctuationCommand::new(phrase, replacement, case_insensitive))
+        .collect()
+}
+
+/// Marks where a spoken "period", "question mark" or "exclamation mark" ended
+/// a sentence, so the word after it is capitalized; removed before injection
+pub const SENTENCE_END: char = '\u{E001}';
+
+/// A spoken command and the punctuation it inserts
+struct PunctuationCommand {
+    phrase: String,
+    case_insensitive: bool,
+    re: Regex,
+    replacement: String,
+}
+
+impl PunctuationCommand {
+    /// The command, with the spaces the punctuation shouldn't have around it
+    ///
+    /// Closing punctuation (`.`, `,`, `)`, ...) takes the space before the
+    /// command, opening brackets the space after it, and new lines and tabs
+    /// both. Other symbols keep their spaces ("well dash known"), unless the
+    /// replacement brings its own (" -- "), which then stand in for them.
+    fn new(phrase: &str, replacement: &str, case_insensitive: bool) -> Self {
+        let flags = if case_insensitive { "(?i)" } else { "" };
+        let (before, after) = match replacement {
+            "." | "," | "?" | "!" | ":" | ";" | ")" | "]" | "}" => (" *", ""),
+            "(" | "[" | "{" => ("", " *"),
+            "\n" | "\t" => (" *", " *"),
+            _ => (
+                if replacement.starts_with(' ') {
+                    " *"
+                } else {
+                    ""
+                },
+                if replacement.ends_with(' ') { " *" } else { "" },
+            ),
+        };
+        let pattern = format!(
+            r"{}{}\b{}\b{}",
+            flags,
+            before,
+            regex::escape(phrase.trim()),
+            after
+        );
+        let replacement = match replacement {
+            "." | "?" | "!" => format!("{}{}", replacement, SENTENCE_END),
+            _ => replacement.to_string(),
+        };
+        Self {
+            phrase: phrase.to_string(),
+            case_insensitive,
+            re: Regex::new(&pattern).unwrap(),
+            replacement,
+        }
+    }
+}
+
+/// Compile spoken phrase → text commands, skipping empty ones
+///
+/// Phrases are matched case-insensitively, longest first so a phrase isn't
+/// shadowed by a shorter one it contains ("thumbs up emoji", "thumbs up").
+pub fn phrase_commands(table: &HashMap<String, String>) -> Vec<(Regex, String)> {
+    let mut phrases: Vec<(&str, &String)> = table
+        .iter()
+        .map(|(phrase, text)| (phrase.trim(), text))
+        .filter(|(phrase, text)| !phrase.is_empty() && !text.is_empty())
+        .collect();
+    phrases.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    phrases
+        .into_iter()
+        .filter_map(|(phrase, text)| {
+            Regex::new(&format!(r"(?i)\b{}\b", regex::escape(phrase)))
+                .ok()
+                .map(|re| (re, text.clone()))
+        })
+        .collect()
+}
+
+/// Apply compiled phrase commands, inserting their text literally
+fn replace_phrases(text: String, commands: &[(Regex, String)]) -> String {
+    commands.iter().fold(text, |text, (re, replacement)| {
+        re.replace_all(&text, regex::NoExpand(replacement))
+            .to_string()
+    })
+}
+
 /// Text processor that applies word overrides and punctuation commands
 ///
 /// This handles transforming transcribed text according to user preferences:
 /// - Word overrides: Replace specific words/phrases (case-insensitive)
-/// - Punctuation commands: Convert spoken commands to punctuation
+/// - Punctuation commands: Convert spoken commands to punctuation, in the
+///   locale matching the dictation language
 pub struct TextProcessor {
     word_overrides: Vec<(Regex, String)>,
-    punctuation: Vec<(Regex, &'static str)>,
+    regex_rules: Vec<(Regex, String)>,
+    punctuation: Vec<PunctuationCommand>,
+    punctuation_enabled: bool,
+    emoji: Vec<(Regex, String)>,
+    symbols: Vec<(Regex, String)>,
+    symbol_stage: SymbolStage,
+    snippets: Vec<(Regex, String)>,
+    scratch: Option<ScratchCommand>,
+    terminal_punctuation: Option<String>,
+    convert_numbers: bool,
+    capitalize_sentences: bool,
+    case_commands: bool,
+    capitalization_commands: bool,
 }
 
 impl TextProcessor {
-    pub fn new(overrides: &HashMap<String, String>) -> Self {
+    pub fn new(overrides: &HashMap<String, String>, language: Option<&str>) -> Self {
         // Compile word overrides into regexes
         let word_overrides = overrides
             .iter()
@@ -24,68 +230,263 @@ impl TextProcessor {
             })
             .collect();
 
-        let punctuation = vec![
-            (Regex::new(r"\bperiod\b").unwrap(), "."),
-            (Regex::new(r"\bcomma\b").unwrap(), ","),
-            (Regex::new(r"\bquestion mark\b").unwrap(), "?"),
-            (Regex::new(r"\bexclamation mark\b").unwrap(), "!"),
-            (Regex::new(r"\bcolon\b").unwrap(), ":"),
-            (Regex::new(r"\bsemicolon\b").unwrap(), ";"),
-            (Regex::new(r"\bnew line\b").unwrap(), "\n"),
-            (Regex::new(r"\btab\b").unwrap(), "\t"),
-            (Regex::new(r"\bdash\b").unwrap(), "-"),
-            (Regex::new(r"\bunderscore\b").unwrap(), "_"),
-            (Regex::new(r"\bopen paren\b").unwrap(), "("),
-            (Regex::new(r"\bclose paren\b").unwrap(), ")"),
-            (Regex::new(r"\bopen bracket\b").unwrap(), "["),
-            (Regex::new(r"\bclose bracket\b").unwrap(), "]"),
-            (Regex::new(r"\bopen brace\b").unwrap(), "{"),
-            (Regex::new(r"\bclose brace\b").unwrap(), "}"),
-            (Regex::new(r"\bat symbol\b").unwrap(), "@"),
-            (Regex::new(r"\bhash\b").unwrap(), "#"),
-            (Regex::new(r"\bplus\b").unwrap(), "+"),
-            (Regex::new(r"\bequals\b").unwrap(), "="),
-            (Regex::new(r"\basterisk\b").unwrap(), "*"),
-            (Regex::new(r"\bampersand\b").unwrap(), "&"),
-            (Regex::new(r"\bpercent\b").unwrap(), "%"),
-            (Regex::new(r"\bdollar sign\b").unwrap(), "$"),
-            (Regex::new(r"\bbackslash\b").unwrap(), "\\"),
-            (Regex::new(r"\bslash\b").unwrap(), "/"),
-            (Regex::new(r"\bpipe\b").unwrap(), "|"),
-            (Regex::new(r"\bcaret\b").unwrap(), "^"),
-            (Regex::new(r"\btilde\b").unwrap(), "~"),
-            (Regex::new(r"\bbacktick\b").unwrap(), "`"),
-            (Regex::new(r"\bquote\b").unwrap(), "\""),
-            (Regex::new(r"\bsingle quote\b").unwrap(), "'"),
-            (Regex::new(r"\bless than\b").unwrap(), "<"),
-            (Regex::new(r"\bgreater than\b").unwrap(), ">"),
-        ];
+        let punctuation = punctuation_commands(language);
 
         Self {
             word_overrides,
+            regex_rules: Vec::new(),
             punctuation,
+            punctuation_enabled: true,
+            emoji: emoji::commands(&HashMap::new()),
+            symbols: Vec::new(),
+            symbol_stage: SymbolStage::default(),
+            snippets: Vec::new(),
+            scratch: None,
+            terminal_punctuation: None,
+            convert_numbers: false,
+            capitalize_sentences: false,
+            case_commands: false,
+            capitalization_commands: false,
+        }
+    }
+
+    /// Change the punctuation commands of the language (`punctuation_overrides`)
+    ///
+    /// A phrase in the table gets the new replacement, or is removed when it
+    /// maps to an empty string. New phrases are matched case-insensitively,
+    /// before the table, longest first so that a phrase containing a shorter
+    /// command as a word wins.
+    pub fn with_punctuation_overrides(mut self, overrides: &HashMap<String, String>) -> Self {
+        let mut added: Vec<(&String, &String)> = Vec::new();
+        for (phrase, replacement) in overrides {
+            match self
+                .punctuation
+                .iter()
+                .position(|command| command.phrase.eq_ignore_ascii_case(phrase))
+            {
+                Some(index) if replacement.is_empty() => {
+                    self.punctuation.remove(index);
+                }
+                Some(index) => {
+                    let command = &self.punctuation[index];
+                    self.punctuation[index] = PunctuationCommand::new(
+                        &command.phrase,
+                        replacement,
+                        command.case_insensitive,
+                    );
+                }
+                None if !replacement.is_empty() => added.push((phrase, replacement)),
+                None => {}
+            }
         }
+        added.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+        let added: Vec<PunctuationCommand> = added
+            .into_iter()
+            .map(|(phrase, replacement)| PunctuationCommand::new(phrase, replacement, true))
+            .collect();
+        self.punctuation.splice(0..0, added);
+        self
+    }
+
+    /// Add to or change the built-in emoji commands (`emoji_commands`)
+    pub fn with_emoji_commands(mut self, extensions: &HashMap<String, String>) -> Self {
+        self.emoji = emoji::commands(extensions);
+        self
+    }
+
+    /// Type `commands` (`symbol_commands`) at `stage` of processing
+    pub fn with_symbol_commands(
+        mut self,
+        commands: &HashMap<String, String>,
+        stage: SymbolStage,
+    ) -> Self {
+        self.symbols = phrase_commands(commands);
+        self.symbol_stage = stage;
+        self
+    }
+
+    /// Expand "insert <name>" to the `snippets` of that name
+    pub fn with_snippets(mut self, snippets: &HashMap<String, String>) -> Self {
+        self.snippets = snippets::commands(snippets);
+        self
+    }
+
+    /// Delete the sentence before each `scratch_phrase`, if one is set
+    pub fn with_scratch_phrase(mut self, phrase: Option<&str>) -> Self {
+        self.scratch = phrase.and_then(ScratchCommand::new);
+        self
+    }
+
+    /// Turn spoken punctuation commands off altogether
+    pub fn with_punctuation_commands(mut self, enabled: bool) -> Self {
+        self.punctuation_enabled = enabled;
+        self
+    }
+
+    /// Append `punctuation` to results that don't already end with punctuation
+    pub fn with_terminal_punctuation(mut self, punctuation: Option<&str>) -> Self {
+        self.terminal_punctuation = punctuation.filter(|p| !p.is_empty()).map(str::to_string);
+        self
+    }
+
+    /// Apply `rules` in order after word overrides; patterns that don't
+    /// compile are skipped (config validation reports them)
+    pub fn with_regex_rules(mut self, rules: &[RegexRule]) -> Self {
+        self.regex_rules = rules
+            .iter()
+            .filter_map(|rule| {
+                Regex::new(&rule.pattern)
+                    .ok()
+                    .map(|re| (re, rule.replacement.clone()))
+            })
+            .collect();
+        self
+    }
+
+    /// Write number words as digits (see [`numbers::convert`])
+    pub fn with_number_conversion(mut self, enabled: bool) -> Self {
+        self.convert_numbers = enabled;
+        self
+    }
+
+    /// Format dictated identifiers (see [`case_commands::apply`])
+    pub fn with_case_commands(mut self, enabled: bool) -> Self {
+        self.case_commands = enabled;
+        self
+    }
+
+    /// Change the case of words said after "cap", "all caps" or "no caps"
+    pub fn with_capitalization_commands(mut self, enabled: bool) -> Self {
+        self.capitalization_commands = enabled;
+        self
+    }
+
+    /// Start each sentence with an uppercase letter
+    pub fn with_sentence_capitalization(mut self, enabled: bool) -> Self {
+        self.capitalize_sentences = enabled;
+        self
     }
 
     /// Process text by applying all transformations
     pub fn process(&self, text: &str) -> String {
-        let mut result = text.to_string();
+        // Snippets are set aside until the end, their text typed as written
+        let mut result = snippets::mark(text, &self.snippets);
+        if self.symbol_stage == SymbolStage::BeforeOverrides {
+            result = replace_phrases(result, &self.symbols);
+        }
 
         // Apply word overrides first
         for (re, replacement) in &self.word_overrides {
             result = re.replace_all(&result, replacement).to_string();
         }
+        for (re, replacement) in &self.regex_rules {
+            result = re.replace_all(&result, replacement.as_str()).to_string();
+        }
+
+        // Spelling and case commands before punctuation, which would take
+        // "dash" and "open bracket"
+        result = spelling::apply(&result);
+        result = case_commands::apply(&result, self.case_commands, self.capitalization_commands);
+        if self.convert_numbers {
+            result = numbers::convert(&result);
+        }
+
+        if self.symbol_stage == SymbolStage::BeforePunctuation {
+            result = replace_phrases(result, &self.symbols);
+        }
+        result = replace_phrases(result, &self.emoji);
 
         // Then apply punctuation commands
-        for (re, replacement) in &self.punctuation {
-            result = re.replace_all(&result, *replacement).to_string();
+        if self.punctuation_enabled {
+            for command in &self.punctuation {
+                result = command
+                    .re
+                    .replace_all(&result, regex::NoExpand(&command.replacement))
+                    .to_string();
+            }
+        }
+        if self.symbol_stage == SymbolStage::AfterPunctuation {
+            result = replace_phrases(result, &self.symbols);
         }
 
+        if let Some(scratch) = &self.scratch {
+            result = scratch.apply(&result);
+        }
+        result = start_sentences(&result);
+        if self.capitalize_sentences {
+            result = capitalize_sentences(&result);
+        }
+        result = result.replace(case_commands::KEEP_CASE, "");
+
         // Normalize whitespace and trim
         result = result.trim().to_string();
 
-        result
+        // Whisper often drops the final period on short phrases
+        if let Some(terminal) = &self.terminal_punctuation
+            && !result.is_empty()
+            && !result.ends_with(is_punctuation)
+            && !snippets::ends_with_snippet(&result)
+        {
+            result.push_str(terminal);
+        }
+
+        snippets::expand(&result, &self.snippets)
+    }
+}
+
+/// Capitalize the word after each spoken sentence end, removing the marks
+fn start_sentences(text: &str) -> String {
+    if !text.contains(SENTENCE_END) {
+        return text.to_string();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut pending = false;
+    for c in text.chars() {
+        if c == SENTENCE_END {
+            pending = true;
+            continue;
+        }
+        if pending && c.is_alphabetic() {
+            result.extend(c.to_uppercase());
+            pending = false;
+            continue;
+        }
+        if !c.is_whitespace() {
+            pending = false;
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Uppercase the first letter of the text and of each sentence after it
+///
+/// A sentence starts after `.`, `!` or `?` followed by whitespace, or on a
+/// new line. Words said with "no caps" are left alone.
+fn capitalize_sentences(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut at_start = true;
+    let mut ended = false;
+    for c in text.chars() {
+        if at_start && c.is_alphanumeric() {
+            result.extend(c.to_uppercase());
+            at_start = false;
+            continue;
+        }
+        if c == '\n' || (ended && c.is_whitespace()) {
+            at_start = true;
+        } else if c == case_commands::KEEP_CASE {
+            at_start = false;
+        }
+        ended = matches!(c, '.' | '!' | '?');
+        result.push(c);
     }
+    result
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || matches!(c, '…' | '。' | '？' | '！' | '¿' | '¡')
 }
 
 #[cfg(test)]
@@ -94,14 +495,44 @@ mod tests {
 
     #[test]
     fn test_punctuation_commands() {
-        let processor = TextProcessor::new(&HashMap::new());
+        let processor = TextProcessor::new(&HashMap::new(), None);
 
-        assert_eq!(processor.process("hello period"), "hello .");
-        assert_eq!(processor.process("hello comma world"), "hello , world");
+        assert_eq!(processor.process("hello period"), "hello.");
+        assert_eq!(processor.process("hello comma world"), "hello, world");
         assert_eq!(
             processor.process("question mark at end question mark"),
-            "? at end ?"
+            "? At end?"
+        );
+    }
+
+    #[test]
+    fn test_punctuation_spacing() {
+        let processor = TextProcessor::new(&HashMap::new(), None);
+
+        assert_eq!(
+            processor.process("hello period how are you question mark fine"),
+            "hello. How are you? Fine"
+        );
+        assert_eq!(
+            processor.process("call open paren x comma y close paren semicolon"),
+            "call (x, y);"
+        );
+        assert_eq!(
+            processor.process("list open bracket one close bracket colon done"),
+            "list [one]: done"
+        );
+        // New lines and tabs take the spaces on both sides
+        assert_eq!(
+            processor.process("dear team comma new line new line thanks period"),
+            "dear team,\n\nthanks."
         );
+        assert_eq!(
+            processor.process("sign off period new line regards"),
+            "sign off.\nRegards"
+        );
+        assert_eq!(processor.process("name tab value"), "name\tvalue");
+        // Other symbols keep their spaces
+        assert_eq!(processor.process("well dash known"), "well - known");
     }
 
     #[test]
@@ -109,7 +540,7 @@ mod tests {
         let mut overrides = HashMap::new();
         overrides.insert("dictator".to_string(), "dctr".to_string());
 
-        let processor = TextProcessor::new(&overrides);
+        let processor = TextProcessor::new(&overrides, None);
 
         assert_eq!(
             processor.process("dictator is cool"),
@@ -126,11 +557,161 @@ mod tests {
         let mut overrides = HashMap::new();
         overrides.insert("dictator".to_string(), "Dictator".to_string());
 
-        let processor = TextProcessor::new(&overrides);
+        let processor = TextProcessor::new(&overrides, None);
 
         assert_eq!(
             processor.process("dictator is great period"),
-            "Dictator is great ."
+            "Dictator is great."
+        );
+    }
+
+    #[test]
+    fn test_localized_punctuation() {
+        let spanish = TextProcessor::new(&HashMap::new(), Some("es"));
+        assert_eq!(spanish.process("hola coma mundo punto"), "hola, mundo.");
+        assert_eq!(spanish.process("uno punto y coma dos"), "uno; dos");
+
+        let german = TextProcessor::new(&HashMap::new(), Some("de"));
+        assert_eq!(german.process("Hallo Komma Welt Punkt"), "Hallo, Welt.");
+
+        // Unknown languages fall back to English commands
+        let fallback = TextProcessor::new(&HashMap::new(), Some("ja"));
+        assert_eq!(fallback.process("hello period"), "hello.");
+    }
+
+    #[test]
+    fn test_punctuation_overrides() {
+        let overrides: HashMap<String, String> = [
+            ("Punkt", "."),
+            ("period", "!"),
+            ("hash", ""),
+            ("dollar sign", "USD"),
+            ("dash dash", " -- "),
+        ]
+        .into_iter()
+        .map(|(phrase, replacement)| (phrase.to_string(), replacement.to_string()))
+        .collect();
+        let processor =
+            TextProcessor::new(&HashMap::new(), None).with_punctuation_overrides(&overrides);
+
+        assert_eq!(processor.process("hello punkt"), "hello.");
+        assert_eq!(processor.process("hello period"), "hello!");
+        assert_eq!(processor.process("hash dollar sign"), "hash USD");
+        // Added before the table, so "dash" doesn't take it apart
+        assert_eq!(processor.process("a dash dash b dash c"), "a -- b - c");
+
+        let processor = TextProcessor::new(&HashMap::new(), None)
+            .with_punctuation_overrides(&overrides)
+            .with_punctuation_commands(false);
+        assert_eq!(processor.process("hello period"), "hello period");
+    }
+
+    #[test]
+    fn test_symbol_commands() {
+        let rules = [RegexRule {
+            pattern: r"(\d) °".to_string(),
+            replacement: "$1°".to_string(),
+        }];
+        let symbols: HashMap<String, String> = [
+            ("degree sign", "°"),
+            ("em dash", "—"),
+            ("smiley", ":-)"),
+            ("pipe pipe", "‖"),
+        ]
+        .into_iter()
+        .map(|(phrase, symbol)| (phrase.to_string(), symbol.to_string()))
+        .collect();
+        let processor = |stage| {
+            TextProcessor::new(&HashMap::new(), None)
+                .with_regex_rules(&rules)
+                .with_symbol_commands(&symbols, stage)
+        };
+
+        let before_punctuation = processor(SymbolStage::BeforePunctuation);
+        assert_eq!(
+            before_punctuation.process("20 Degree sign em dash warm smiley"),
+            "20 ° — warm :-)"
+        );
+        assert_eq!(before_punctuation.process("a pipe pipe b"), "a ‖ b");
+        // "pipe" is a punctuation command, so there's no "pipe pipe" left
+        assert_eq!(
+            processor(SymbolStage::AfterPunctuation).process("a pipe pipe b"),
+            "a | | b"
+        );
+        // Regex rules see the symbol
+        assert_eq!(
+            processor(SymbolStage::BeforeOverrides).process("20 degree sign"),
+            "20°"
+        );
+    }
+
+    #[test]
+    fn test_terminal_punctuation() {
+        let processor =
+            TextProcessor::new(&HashMap::new(), None).with_terminal_punctuation(Some("."));
+
+        assert_eq!(processor.process("hello world"), "hello world.");
+        assert_eq!(processor.process("hello world?"), "hello world?");
+        assert_eq!(processor.process("hello world period"), "hello world.");
+        assert_eq!(processor.process("  "), "");
+    }
+
+    #[test]
+    fn test_regex_rules() {
+        let mut overrides = HashMap::new();
+        overrides.insert("jason".to_string(), "JSON".to_string());
+        let rules = [
+            RegexRule {
+                pattern: r"(?i)\b(um+|uh+)\b,?\s*".to_string(),
+                replacement: String::new(),
+            },
+            // Runs after overrides, so it sees "JSON"
+            RegexRule {
+                pattern: r"\bJSON (\w+)".to_string(),
+                replacement: "$1.json".to_string(),
+            },
+            RegexRule {
+                pattern: "(unclosed".to_string(),
+                replacement: String::new(),
+            },
+        ];
+        let processor = TextProcessor::new(&overrides, None).with_regex_rules(&rules);
+
+        assert_eq!(
+            processor.process("Um, open the uh jason config period"),
+            "open the config.json."
+        );
+    }
+
+    #[test]
+    fn test_sentence_capitalization() {
+        let processor = TextProcessor::new(&HashMap::new(), None)
+            .with_sentence_capitalization(true)
+            .with_capitalization_commands(true);
+
+        assert_eq!(
+            processor.process("hello there. how are you? fine new line thanks"),
+            "Hello there. How are you? Fine\nThanks"
+        );
+        assert_eq!(
+            processor.process("pi is 3.14 exactly"),
+            "Pi is 3.14 exactly"
+        );
+        assert_eq!(
+            processor.process("no caps iPhone sales. all caps ok"),
+            "iphone sales. OK"
+        );
+        // Without the option, only the commands change case
+        assert_eq!(
+            TextProcessor::new(&HashMap::new(), None)
+                .with_capitalization_commands(true)
+                .process("no caps iPhone, cap bob"),
+            "iphone, Bob"
+        );
+        // The commands are opt-in
+        assert_eq!(
+            TextProcessor::new(&HashMap::new(), None).process("put the cap on the bottle"),
+            "put the cap on the bottle"
         );
     }
 }
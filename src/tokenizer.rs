@@ -0,0 +1,106 @@
+/// A word of the transcript, with what it is compared as
+///
+/// Voice commands spanning several words (case formatting, spelling) work on
+/// these rather than a regex over the whole text.
+pub struct Token<'a> {
+    pub text: &'a str,
+    /// Lowercased, without the punctuation Whisper puts around commands
+    pub bare: String,
+    /// Byte offset of the word in the transcript
+    start: usize,
+}
+
+impl<'a> Token<'a> {
+    pub fn new(text: &'a str, start: usize) -> Self {
+        Self {
+            text,
+            bare: text
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase(),
+            start,
+        }
+    }
+
+    fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    /// Punctuation after the word, e.g. the period ending a sentence
+    pub fn trailing_punctuation(&self) -> &'a str {
+        let end = self.text.trim_end_matches(|c: char| !c.is_alphanumeric());
+        &self.text[end.len()..]
+    }
+}
+
+/// The whitespace-separated words of `text`
+pub fn tokenize(text: &str) -> Vec<Token<'_>> {
+    text.split_whitespace()
+        .map(|word| Token::new(word, word.as_ptr() as usize - text.as_ptr() as usize))
+        .collect()
+}
+
+/// Rebuilds a transcript from its tokens, some of them replaced, keeping the
+/// whitespace between them (new lines included)
+pub struct Rewriter<'a> {
+    text: &'a str,
+    out: String,
+    /// Where the tokens written so far end in `text`
+    end: usize,
+}
+
+impl<'a> Rewriter<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            out: String::with_capacity(text.len()),
+            end: 0,
+        }
+    }
+
+    /// The whitespace before the token at `start`; none when everything
+    /// before it was dropped
+    fn separator(&self, start: usize) -> &'a str {
+        if self.out.is_empty() && self.end > 0 {
+            ""
+        } else {
+            &self.text[self.end..start]
+        }
+    }
+
+    /// Write `tokens` as said
+    pub fn keep(&mut self, tokens: &[Token]) {
+        if let (Some(first), Some(last)) = (tokens.first(), tokens.last()) {
+            self.out.push_str(self.separator(first.start));
+            self.out.push_str(&self.text[first.start..last.end()]);
+            self.end = last.end();
+        }
+    }
+
+    /// Write `replacement` in place of `tokens`, or drop them (and the
+    /// whitespace before them) if it's empty
+    pub fn replace(&mut self, tokens: &[Token], replacement: &str) {
+        if let (Some(first), Some(last)) = (tokens.first(), tokens.last()) {
+            if !replacement.is_empty() {
+                self.out.push_str(self.separator(first.start));
+                self.out.push_str(replacement);
+            }
+            self.end = last.end();
+        }
+    }
+
+    pub fn finish(mut self) -> String {
+        if !self.out.is_empty() || self.end == 0 {
+            self.out.push_str(&self.text[self.end..]);
+        }
+        self.out
+    }
+}
+
+/// Whether the words at `index` say `phrase`
+pub fn says(tokens: &[Token], index: usize, phrase: &[&str]) -> bool {
+    tokens.len() >= index + phrase.len()
+        && phrase
+            .iter()
+            .zip(&tokens[index..])
+            .all(|(word, token)| token.bare == *word)
+}
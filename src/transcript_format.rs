@@ -0,0 +1,159 @@
+use crate::transcription::Transcript;
+use anyhow::Result;
+use serde_json::json;
+
+/// How `dictator transcribe` and `dictator batch` write a transcript
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TranscriptFormat {
+    /// The processed text, as a dictation would type it
+    #[default]
+    Text,
+    /// SubRip subtitles, one cue per segment
+    Srt,
+    /// WebVTT subtitles, one cue per segment
+    Vtt,
+    /// The text, language and timed segments
+    Json,
+}
+
+impl TranscriptFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "text" => Ok(Self::Text),
+            "srt" => Ok(Self::Srt),
+            "vtt" => Ok(Self::Vtt),
+            "json" => Ok(Self::Json),
+            other => anyhow::bail!(
+                "Unknown format {:?} (expected \"text\", \"srt\", \"vtt\" or \"json\")",
+                other
+            ),
+        }
+    }
+
+    /// Extension of the file `dictator batch` writes next to each recording
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+            Self::Json => "json",
+        }
+    }
+
+    /// Render a transcript; subtitles need the segment timestamps, which only
+    /// backends returning `verbose_json` segments provide
+    pub fn render(self, transcript: &Transcript) -> Result<String> {
+        if matches!(self, Self::Srt | Self::Vtt) && transcript.segments.is_empty() {
+            anyhow::bail!("The transcription backend returned no segment timestamps for subtitles");
+        }
+
+        let cues = || {
+            transcript
+                .segments
+                .iter()
+                .filter(|segment| !segment.text.trim().is_empty())
+        };
+        Ok(match self {
+            Self::Text => format!("{}\n", transcript.text),
+            Self::Srt => cues()
+                .enumerate()
+                .map(|(index, segment)| {
+                    format!(
+                        "{}\n{} --> {}\n{}\n\n",
+                        index + 1,
+                        timestamp(segment.start, ','),
+                        timestamp(segment.end, ','),
+                        segment.text.trim()
+                    )
+                })
+                .collect(),
+            Self::Vtt => {
+                let mut vtt = "WEBVTT\n\n".to_string();
+                for segment in cues() {
+                    vtt.push_str(&format!(
+                        "{} --> {}\n{}\n\n",
+                        timestamp(segment.start, '.'),
+                        timestamp(segment.end, '.'),
+                        segment.text.trim()
+                    ));
+                }
+                vtt
+            }
+            Self::Json => {
+                let segments: Vec<_> = cues()
+                    .map(|segment| {
+                        json!({
+                            "start": segment.start,
+                            "end": segment.end,
+                            "text": segment.text.trim(),
+                        })
+                    })
+                    .collect();
+                let json = json!({
+                    "text": transcript.text,
+                    "language": transcript.language,
+                    "segments": segments,
+                });
+                format!("{:#}\n", json)
+            }
+        })
+    }
+}
+
+/// `HH:MM:SS,mmm`, with `.` before the milliseconds for WebVTT
+fn timestamp(seconds: f32, separator: char) -> String {
+    let millis = (f64::from(seconds.max(0.0)) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::TranscriptSegment;
+
+    #[test]
+    fn test_subtitles() {
+        let segment = |start, end, text: &str| TranscriptSegment {
+            start,
+            end,
+            text: text.to_string(),
+            ..Default::default()
+        };
+        let transcript = Transcript {
+            text: "Welcome everyone. Let's start.".to_string(),
+            language: Some("en".to_string()),
+            segments: vec![
+                segment(0.0, 2.5, " Welcome everyone."),
+                segment(2.5, 3.0, " "),
+                segment(3661.25, 3663.0, " Let's start."),
+            ],
+        };
+
+        assert_eq!(
+            TranscriptFormat::Srt.render(&transcript).unwrap(),
+            "1\n00:00:00,000 --> 00:00:02,500\nWelcome everyone.\n\n\
+             2\n01:01:01,250 --> 01:01:03,000\nLet's start.\n\n"
+        );
+        assert_eq!(
+            TranscriptFormat::Vtt.render(&transcript).unwrap(),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:02.500\nWelcome everyone.\n\n\
+             01:01:01.250 --> 01:01:03.000\nLet's start.\n\n"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&TranscriptFormat::Json.render(&transcript).unwrap()).unwrap();
+        assert_eq!(json["segments"][1]["start"], 3661.25);
+        assert_eq!(json["language"], "en");
+
+        let text_only = Transcript::from_text("Hi".to_string());
+        assert_eq!(TranscriptFormat::Text.render(&text_only).unwrap(), "Hi\n");
+        assert!(TranscriptFormat::Srt.render(&text_only).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use vosk::{CompleteResult, DecodingState, Model, Recognizer};
+
+/// A Vosk model loaded into memory (`transcription_backend = "vosk"`)
+///
+/// Vosk recognizes speech incrementally, so it is fed the recording's chunks
+/// while recording and has the text ready as soon as it stops. Its small
+/// models run comfortably on a laptop CPU, fully offline.
+pub struct VoskModel {
+    model: Model,
+    sample_rate: u32,
+}
+
+impl VoskModel {
+    /// Load the model directory at `model_path`; audio arrives at `sample_rate`
+    pub fn load(model_path: &Path, sample_rate: u32) -> Result<Self> {
+        let path = model_path
+            .to_str()
+            .context("model_path is not valid UTF-8")?;
+        let model = Model::new(path)
+            .with_context(|| format!("Failed to load Vosk model {:?}", model_path))?;
+
+        tracing::info!("Loaded Vosk model {:?}", model_path);
+        Ok(Self { model, sample_rate })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Recognize chunks until the channel closes, then return the text
+    pub fn recognize_stream(
+        self: Arc<Self>,
+        mut audio: mpsc::UnboundedReceiver<Vec<f32>>,
+    ) -> Result<String> {
+        self.recognize_chunks(std::iter::from_fn(|| audio.blocking_recv()))
+    }
+
+    /// Recognize a whole recording at once, e.g. for a retry
+    pub fn recognize(&self, samples: &[f32]) -> Result<String> {
+        // Fed in pieces, as it would have been while recording
+        self.recognize_chunks(samples.chunks(self.sample_rate as usize / 2))
+    }
+
+    fn recognize_chunks<C: AsRef<[f32]>>(&self, chunks: impl Iterator<Item = C>) -> Result<String> {
+        let mut recognizer = Recognizer::new(&self.model, self.sample_rate as f32)
+            .context("Failed to create Vosk recognizer")?;
+
+        let mut text = Vec::new();
+        for chunk in chunks {
+            if matches!(
+                accept(&mut recognizer, chunk.as_ref())?,
+                DecodingState::Finalized
+            ) {
+                // Vosk ends an utterance at each pause; keep what it recognized
+                text.push(result_text(recognizer.result()));
+            }
+        }
+        text.push(result_text(recognizer.final_result()));
+
+        let text = join(text);
+        tracing::info!("Transcription complete: {} chars", text.len());
+        Ok(text)
+    }
+}
+
+fn accept(recognizer: &mut Recognizer, samples: &[f32]) -> Result<DecodingState> {
+    let pcm: Vec<i16> = samples
+        .iter()
+        .map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+        .collect();
+    recognizer
+        .accept_waveform(&pcm)
+        .map_err(|e| anyhow::anyhow!("Vosk rejected the audio: {:?}", e))
+}
+
+fn result_text(result: CompleteResult) -> String {
+    result
+        .single()
+        .map(|result| result.text.to_string())
+        .unwrap_or_default()
+}
+
+fn join(parts: Vec<String>) -> String {
+    let parts: Vec<&str> = parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect();
+    parts.join(" ")
+}
@@ -0,0 +1,187 @@
+use crate::audio::pcm_bytes;
+use crate::transcription::{RequestExtras, TranscriptionConfig};
+use anyhow::{Context, Result};
+use futures::{SinkExt, StreamExt};
+use reqwest::Url;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::{self, Message};
+
+/// The server expects 16 kHz mono PCM
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// Endpoint below `api_url`, the same path as the upload API
+const TRANSCRIPTION_PATH: &str = "/audio/transcriptions";
+
+/// Streaming transcription over a WebSocket (`transcription_backend = "websocket"`)
+///
+/// Speaks faster-whisper-server's protocol: audio goes out as binary frames of
+/// 16-bit little-endian PCM while recording, and the server answers with JSON
+/// `{"text": ...}` holding its hypothesis for everything heard so far. When
+/// the recording stops, the socket is closed and the last hypothesis received
+/// before the server acknowledges is the transcript.
+#[derive(Clone)]
+pub struct WebSocketClient {
+    api_url: String,
+    api_key: String,
+    timeout: Duration,
+    extras: RequestExtras,
+}
+
+impl WebSocketClient {
+    pub fn new(api_url: &str, api_key: &str, timeout_secs: u64, extras: RequestExtras) -> Self {
+        Self {
+            api_url: api_url.to_string(),
+            api_key: api_key.to_string(),
+            timeout: Duration::from_secs(timeout_secs),
+            extras,
+        }
+    }
+
+    /// Send chunks until the channel closes, then return the final text
+    pub async fn stream(
+        self,
+        config: TranscriptionConfig,
+        mut audio: mpsc::UnboundedReceiver<Vec<f32>>,
+    ) -> Result<String> {
+        let url = websocket_url(&self.api_url, &config, &self.extras)?;
+        let mut request = url
+            .as_str()
+            .into_client_request()
+            .context("Invalid WebSocket request")?;
+        let headers = request.headers_mut();
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", self.api_key)
+                .parse()
+                .context("api_key is not a valid header value")?,
+        );
+        for (name, value) in &self.extras.headers {
+            headers.insert(name, value.clone());
+        }
+
+        let (socket, _) =
+            tokio::time::timeout(self.timeout, tokio_tungstenite::connect_async(request))
+                .await
+                .context("Timed out connecting to the streaming API")?
+                .with_context(|| format!("Failed to connect to {}", url))?;
+        tracing::debug!("Streaming to {}", url);
+        let (mut sink, mut messages) = socket.split();
+
+        let mut text = String::new();
+        // Forward the recording as it arrives, noting hypotheses meanwhile
+        loop {
+            tokio::select! {
+                chunk = audio.recv() => match chunk {
+                    Some(chunk) => sink
+                        .send(Message::binary(pcm_bytes(&chunk)))
+                        .await
+                        .context("Failed to send audio to the streaming API")?,
+                    None => break,
+                },
+                message = messages.next() => {
+                    if !read_hypothesis(message, &mut text)? {
+                        anyhow::bail!("The streaming API closed the connection while recording");
+                    }
+                }
+            }
+        }
+
+        // The recording has stopped; wait for the server to finish up
+        sink.close()
+            .await
+            .context("Failed to end the audio stream")?;
+        tokio::time::timeout(self.timeout, async {
+            while read_hypothesis(messages.next().await, &mut text)? {}
+            Ok::<_, anyhow::Error>(())
+        })
+        .await
+        .context("Timed out waiting for the final transcription")??;
+
+        tracing::info!("Transcription complete: {} chars", text.len());
+        Ok(text.trim().to_string())
+    }
+}
+
+/// Note the text of a hypothesis; returns false once the connection has closed
+fn read_hypothesis(
+    message: Option<Result<Message, tungstenite::Error>>,
+    text: &mut String,
+) -> Result<bool> {
+    match message {
+        None | Some(Ok(Message::Close(_))) => Ok(false),
+        Some(Ok(message @ Message::Text(_))) => {
+            let response: serde_json::Value = serde_json::from_str(message.to_text()?)
+                .context("The streaming API sent invalid JSON")?;
+            if let Some(hypothesis) = response.get("text").and_then(|text| text.as_str()) {
+                tracing::debug!("Partial transcription: {}", hypothesis);
+                *text = hypothesis.to_string();
+            }
+            Ok(true)
+        }
+        Some(Ok(_)) => Ok(true),
+        Some(Err(e)) => Err(e).context("Streaming transcription failed"),
+    }
+}
+
+/// `api_url` with a WebSocket scheme, the endpoint path and the request options
+fn websocket_url(
+    api_url: &str,
+    config: &TranscriptionConfig,
+    extras: &RequestExtras,
+) -> Result<Url> {
+    let mut url = Url::parse(&format!(
+        "{}{}",
+        api_url.trim_end_matches('/'),
+        TRANSCRIPTION_PATH
+    ))
+    .with_context(|| format!("Invalid api_url: {}", api_url))?;
+    let scheme = match url.scheme() {
+        "http" | "ws" => "ws",
+        "https" | "wss" => "wss",
+        other => anyhow::bail!("api_url has an unsupported scheme: {}", other),
+    };
+    url.set_scheme(scheme)
+        .map_err(|_| anyhow::anyhow!("Invalid api_url: {}", api_url))?;
+
+    {
+        let mut query = url.query_pairs_mut();
+        query.append_pair("model", &config.model);
+        query.append_pair("response_format", "json");
+        if !config.language.is_empty() {
+            query.append_pair("language", &config.language);
+        }
+        for (name, value) in &extras.query {
+            query.append_pair(name, value);
+        }
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket_url() {
+        let config = TranscriptionConfig {
+            model: "Systran/faster-whisper-base".to_string(),
+            prompt: String::new(),
+            language: "en".to_string(),
+            keywords: Vec::new(),
+            translate: false,
+        };
+        let url = websocket_url(
+            "https://whisper.example/v1/",
+            &config,
+            &RequestExtras::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "wss://whisper.example/v1/audio/transcriptions?model=Systran%2Ffaster-whisper-base&response_format=json&language=en"
+        );
+        assert!(websocket_url("ftp://example", &config, &RequestExtras::default()).is_err());
+    }
+}